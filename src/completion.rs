@@ -0,0 +1,70 @@
+// Tab completion of the name being typed at the end of the line, from the
+// commands and functions the lexer knows and the names given by the user.
+
+use crate::spellings::*;
+
+// Return where in the line the name being completed starts, and the names
+// it can be completed to, sorted. A name starts with a lowercase letter
+// and goes on with letters, digits and underscores, as in the lexer.
+pub fn complete(line: &str, user_names: &[String]) -> (usize, Vec<String>) {
+	let word_start = line.char_indices()
+		.rev()
+		.take_while(|(_, c)| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '_')
+		.last()
+		.map_or(line.len(), |(i, _)| i);
+	
+	// Digits and underscores before the first letter are not part of it,
+	// as in "3sq" for 3 * sqrt.
+	let start = line[word_start..].find(|c: char| c.is_ascii_lowercase()).map_or(line.len(), |i| word_start + i);
+	let prefix = &line[start..];
+	
+	if prefix.is_empty() {
+		return (start, Vec::new());
+	}
+	
+	let mut candidates: Vec<String> = COMMAND_SPELLINGS.iter().map(|(spelling, _)| *spelling)
+		.chain(FUNCTION_SPELLINGS.iter().map(|(spelling, _)| *spelling))
+		.chain(user_names.iter().map(String::as_str))
+		.filter(|name| name.starts_with(prefix))
+		.map(String::from)
+		.collect();
+	candidates.sort();
+	candidates.dedup();
+	(start, candidates)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn function_and_command_names_are_completed() {
+		let (start, candidates) = complete("ma", &[]);
+		assert_eq!(start, 0);
+		assert!(candidates.contains(&String::from("max")));
+		assert!(!candidates.contains(&String::from("min")));
+		assert_eq!(complete("me", &[]), (0, vec![String::from("mean")]));
+		assert_eq!(complete("sq", &[]), (0, vec![String::from("sqrt")]));
+		assert_eq!(complete("hel", &[]), (0, vec![String::from("help")]));
+	}
+	
+	#[test]
+	fn name_at_end_of_line_is_completed() {
+		assert_eq!(complete("2 * sq", &[]), (4, vec![String::from("sqrt")]));
+		assert_eq!(complete("max(1, sq", &[]), (7, vec![String::from("sqrt")]));
+		assert_eq!(complete("3sq", &[]), (1, vec![String::from("sqrt")]));
+	}
+	
+	#[test]
+	fn user_names_are_completed() {
+		let names = vec![String::from("total"), String::from("tax_rate")];
+		assert_eq!(complete("ta", &names), (0, vec![String::from("tan"), String::from("tand"), String::from("tanh"), String::from("tanturn"), String::from("tax_rate")]));
+	}
+	
+	#[test]
+	fn nothing_is_offered_without_a_name() {
+		assert_eq!(complete("", &[]), (0, Vec::<String>::new()));
+		assert_eq!(complete("1 + ", &[]), (4, Vec::<String>::new()));
+		assert_eq!(complete("zzz", &[]), (0, Vec::<String>::new()));
+	}
+}
@@ -3,15 +3,25 @@ use std::fmt::Formatter;
 
 #[derive(Debug)]
 pub struct Error {
-	pub description: String
+	pub description: String,
+	// Where in the input line the error was found, counting characters
+	// from 0, if it was found in the input at all.
+	pub column: Option<usize>
 }
 
 impl Error {
 	pub fn new(s: &str) -> Self {
 		Self {
-			description: String::from(s)
+			description: String::from(s),
+			column: None
 		}
 	}
+	
+	// Attach the column where the error was found, unless it already has one.
+	pub fn at_column(mut self, column: usize) -> Self {
+		self.column.get_or_insert(column);
+		self
+	}
 }
 
 impl fmt::Display for Error {
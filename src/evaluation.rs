@@ -1,38 +1,185 @@
 use crate::errors::Error;
 use crate::lexer::*;
 use crate::parser::*;
-use assert_approx_eq::assert_approx_eq;
+use crate::random::Random;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::env;
+use std::f64::consts::TAU;
+use std::fmt;
 use utility::*;
 
 type EvaluationResult = Result<f64, Error>;
 
+// The name under which the latest result can be referred to.
+pub const ANS: &str = "ans";
+
+// The number of subintervals for integ unless set otherwise, and the most
+// that can be set.
+pub const DEFAULT_STEPS: usize = 1000;
+pub const MAX_STEPS: usize = 1_000_000;
+
+// The unit of angles that sin, cos and tan take, and that their inverses
+// and atan2 give.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AngleMode {
+	Radians,
+	Degrees
+}
+
+// How round() picks an integer: one of the halfway rules for values
+// halfway between two integers, or always in one direction.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RoundMode {
+	// Halfway away from zero, as f64::round. This is the default.
+	HalfAway,
+	HalfEven,
+	// Halfway towards positive infinity.
+	HalfUp,
+	// Halfway towards negative infinity.
+	HalfDown,
+	Ceil,
+	Floor,
+	Trunc
+}
+
+pub const ROUND_MODES: &[(&str, RoundMode)] = &[
+	("halfaway", RoundMode::HalfAway),
+	("halfeven", RoundMode::HalfEven),
+	("halfup", RoundMode::HalfUp),
+	("halfdown", RoundMode::HalfDown),
+	("ceil", RoundMode::Ceil),
+	("floor", RoundMode::Floor),
+	("trunc", RoundMode::Trunc)
+];
+
+impl fmt::Display for RoundMode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (name, _) = ROUND_MODES.iter().find(|(_, mode)| mode == self).expect("RoundMode::fmt(): logic error.");
+		write!(f, "{}", name)
+	}
+}
+
+// Everything an expression can refer to during evaluation.
+pub struct Context {
+	pub variables: HashMap<String, f64>,
+	pub labels: HashMap<String, f64>,
+	// The latest result, which is kept up to date by the runner.
+	pub ans: Option<f64>,
+	// The number of subintervals that integ divides its range into.
+	pub steps: usize,
+	pub angle_mode: AngleMode,
+	pub round_mode: RoundMode,
+	// The source of the choices of functions like choose.
+	pub random: Random,
+	reads: HashSet<String>
+}
+
+impl Context {
+	pub fn new() -> Self {
+		Self {
+			variables: HashMap::<String, f64>::new(),
+			labels: HashMap::<String, f64>::new(),
+			ans: None,
+			steps: DEFAULT_STEPS,
+			angle_mode: AngleMode::Radians,
+			round_mode: RoundMode::HalfAway,
+			random: Random::from_time(),
+			reads: HashSet::<String>::new()
+		}
+	}
+	
+	// Look up a variable, remembering that it was used. ANS is looked up
+	// like a variable but never counts as one.
+	pub fn read(&mut self, var: &str) -> Option<f64> {
+		if var == ANS {
+			return self.ans;
+		}
+		
+		self.reads.insert(String::from(var));
+		self.variables.get(var).copied()
+	}
+	
+	// Evaluate an expression for each of the given values of a variable,
+	// which is bound only meanwhile: a variable of the same name is hidden,
+	// and restored afterwards.
+	pub fn evaluate_bound<I: Iterator<Item = f64>>(&mut self, var: &str, values: I, body: &Expression) -> Result<Vec<f64>, Error> {
+		let outer_value = self.variables.remove(var);
+		let outer_read = self.reads.contains(var);
+		
+		let results: Result<Vec<f64>, Error> = values.map(|value| {
+			self.variables.insert(String::from(var), value);
+			body.evaluate(self)
+		}).collect();
+		
+		match outer_value {
+			Some(value) => { self.variables.insert(String::from(var), value); },
+			None => { self.variables.remove(var); }
+		}
+		
+		if !outer_read {
+			self.reads.remove(var);
+		}
+		
+		results
+	}
+	
+	// Convert an angle in the current angle mode to radians.
+	fn angle_to_radians(&self, angle: f64) -> f64 {
+		match self.angle_mode {
+			AngleMode::Radians => angle,
+			AngleMode::Degrees => angle.to_radians()
+		}
+	}
+	
+	// Convert an angle in radians to the current angle mode.
+	fn angle_from_radians(&self, angle: f64) -> f64 {
+		match self.angle_mode {
+			AngleMode::Radians => angle,
+			AngleMode::Degrees => angle.to_degrees()
+		}
+	}
+	
+	// Return the defined variables that no expression has read so far, sorted by name.
+	pub fn unread_variables(&self) -> Vec<String> {
+		let mut unread: Vec<String> = self.variables.keys().filter(|v| !self.reads.contains(*v)).cloned().collect();
+		unread.sort();
+		unread
+	}
+}
+
 pub trait Evaluable {
-	fn evaluate(&self, variables: &HashMap<char, f64>) -> EvaluationResult;
+	fn evaluate(&self, context: &mut Context) -> EvaluationResult;
 }
 
 impl Evaluable for Expression {
-	fn evaluate(&self, variables: &HashMap<char, f64>) -> EvaluationResult {
+	fn evaluate(&self, context: &mut Context) -> EvaluationResult {
 		match &self {
-			Expression::ParenExpr(e) => e.evaluate(variables),
-			Expression::UnaryExpr(e) => e.evaluate(variables),
-			Expression::BinaryExpr(e) => e.evaluate(variables),
-			Expression::FunctionExpr(e) => e.evaluate(variables),
-			Expression::VariableExpr(e) => e.evaluate(variables),
-			Expression::LiteralExpr(e) => e.evaluate(variables)
+			Expression::ParenExpr(e) => e.evaluate(context),
+			Expression::UnaryExpr(e) => e.evaluate(context),
+			Expression::BinaryExpr(e) => e.evaluate(context),
+			Expression::FunctionExpr(e) => e.evaluate(context),
+			Expression::VariableExpr(e) => e.evaluate(context),
+			Expression::ConstantExpr(e) => e.evaluate(context),
+			Expression::LabelExpr(e) => e.evaluate(context),
+			Expression::LiteralExpr(e) => e.evaluate(context),
+			Expression::ReduceExpr(e) => e.evaluate(context),
+			Expression::IntegralExpr(e) => e.evaluate(context),
+			Expression::EnvExpr(e) => e.evaluate(context),
+			Expression::CommandValueExpr(e) => e.evaluate(context)
 		}
 	}
 }
 
 impl Evaluable for ParenExpression {
-	fn evaluate(&self, variables: &HashMap<char, f64>) -> EvaluationResult {
-		self.expr.evaluate(variables)
+	fn evaluate(&self, context: &mut Context) -> EvaluationResult {
+		self.expr.evaluate(context)
 	}
 }
 
 impl Evaluable for UnaryExpression {
-	fn evaluate(&self, variables: &HashMap<char, f64>) -> EvaluationResult {
-		let inner_result = self.expr.evaluate(variables)?;
+	fn evaluate(&self, context: &mut Context) -> EvaluationResult {
+		let inner_result = self.expr.evaluate(context)?;
 		
 		match &self.op {
 			OperatorType::Plus => Ok(inner_result),
@@ -42,95 +189,486 @@ impl Evaluable for UnaryExpression {
 	}
 }
 
+impl Expression {
+	// Evaluate an expression as a whole. Infinities, such as the constant
+	// inf, may appear within it as in 1/inf, but not as its result.
+	pub fn evaluate_finite(&self, context: &mut Context) -> EvaluationResult {
+		let result = self.evaluate(context)?;
+		
+		if result.is_nan() {
+			error("the result is undefined")
+		} else {
+			verify_result(result, "the result is infinite")
+		}
+	}
+}
+
 impl Evaluable for BinaryExpression {
-	fn evaluate(&self, variables: &HashMap<char, f64>) -> EvaluationResult {
-		let left_result = self.left.evaluate(variables)?;
-		let right_result = self.right.evaluate(variables)?;
+	// A chain like "1+2+3+..." is parsed into a tree nested to the left, as
+	// deep as the chain is long. Collect the chain and evaluate it from the
+	// leftmost operand up, so that only the right operands recurse.
+	fn evaluate(&self, context: &mut Context) -> EvaluationResult {
+		let mut chain = vec![self];
+		let mut leftmost = &self.left;
+		
+		while let Expression::BinaryExpr(e) = leftmost {
+			chain.push(e);
+			leftmost = &e.left;
+		}
+		
+		let mut result = leftmost.evaluate(context)?;
+		
+		for e in chain.iter().rev() {
+			let right_result = e.right.evaluate(context)?;
+			result = e.apply(result, right_result)?;
+		}
 		
+		Ok(result)
+	}
+}
+
+impl BinaryExpression {
+	pub fn apply(&self, left_result: f64, right_result: f64) -> EvaluationResult {
 		match self.op {
 			OperatorType::Plus => verify_result(left_result + right_result, "arithmetic overflow during addition"),
 			OperatorType::Minus => verify_result(left_result - right_result, "arithmetic overflow during subtraction"),
 			OperatorType::Times => verify_result(left_result * right_result, "arithmetic overflow during multiplication"),
 			OperatorType::DividedBy => verify_result(left_result / right_result, "arithmetic overflow during division"),
-			OperatorType::Power => verify_result(left_result.powf(right_result), "result of exponentiation is undefined"),
+			OperatorType::FloorDiv => verify_result((left_result / right_result).floor(), "arithmetic overflow during division"),
+			OperatorType::Modulo => verify_result(left_result.rem_euclid(right_result), "remainder of division by zero"),
+			OperatorType::Power => verify_result(compute_power(left_result, right_result), "result of exponentiation is undefined"),
+			OperatorType::Less => Ok(truth(left_result < right_result)),
+			OperatorType::LessOrEqual => Ok(truth(left_result <= right_result)),
+			OperatorType::Greater => Ok(truth(left_result > right_result)),
+			OperatorType::GreaterOrEqual => Ok(truth(left_result >= right_result)),
+			OperatorType::Equal => Ok(truth(left_result == right_result)),
+			OperatorType::NotEqual => Ok(truth(left_result != right_result)),
 			_ => { panic!("BinaryExression::evaluate: parser is in an invalid state."); }
 		}
 	}
 }
 
 impl Evaluable for FunctionExpression {
-    fn evaluate(&self, variables: &HashMap<char, f64>) -> EvaluationResult {
+    fn evaluate(&self, context: &mut Context) -> EvaluationResult {
+		// Only the selected branch is evaluated, so that the other one may
+		// be undefined, as in "if(x, 1/x, 0)".
+		if self.func == FunctionType::If {
+			require_fixed_args(self.args.len(), 3, "if")?;
+			let condition = self.args[0].evaluate(context)?;
+			return self.args[if condition != 0.0 { 1 } else { 2 }].evaluate(context);
+		}
+		
 		let mut args: Vec<f64> = Vec::new();
 		
 		for	arg in &self.args {
-			args.push(arg.evaluate(variables)?);
+			args.push(arg.evaluate(context)?);
 		}
 
-        Ok(match &self.func {
+        let result = match &self.func {
 			FunctionType::Abs => {
 				require_fixed_args(args.len(), 1, "abs")?;
 				args[0].abs()
 			},
+			FunctionType::AcosDeg => {
+				require_fixed_args(args.len(), 1, "acosd")?;
+				verify_result(args[0].acos().to_degrees(), "acosd: argument must be between -1..1")?
+			},
+			FunctionType::Acosh => {
+				require_fixed_args(args.len(), 1, "acosh")?;
+				verify_result(args[0].acosh(), "acosh: argument must be at least 1")?
+			},
 			FunctionType::ArcCos => {
 				require_fixed_args(args.len(), 1, "arccos")?;
-				verify_result(args[0].acos(), "arccos: argument must be between -1..1")?
+				context.angle_from_radians(verify_result(args[0].acos(), "arccos: argument must be between -1..1")?)
 			},
 			FunctionType::ArcSin => {
 				require_fixed_args(args.len(), 1, "arcsin")?;
-				verify_result(args[0].asin(), "arcsin: argument must be between -1..1")?
+				context.angle_from_radians(verify_result(args[0].asin(), "arcsin: argument must be between -1..1")?)
 			},
 			FunctionType::ArcTan => {
 				require_fixed_args(args.len(), 1, "arctan")?;
-				args[0].atan()
+				context.angle_from_radians(args[0].atan())
+			},
+			FunctionType::AsinDeg => {
+				require_fixed_args(args.len(), 1, "asind")?;
+				verify_result(args[0].asin().to_degrees(), "asind: argument must be between -1..1")?
+			},
+			FunctionType::Asinh => {
+				require_fixed_args(args.len(), 1, "asinh")?;
+				args[0].asinh()
+			},
+			FunctionType::Atan2 => {
+				require_fixed_args(args.len(), 2, "atan2")?;
+				// atan2(0, 0) is 0, as defined by f64::atan2.
+				context.angle_from_radians(args[0].atan2(args[1]))
+			},
+			FunctionType::AtanDeg => {
+				require_fixed_args(args.len(), 1, "atand")?;
+				args[0].atan().to_degrees()
+			},
+			FunctionType::Atanh => {
+				require_fixed_args(args.len(), 1, "atanh")?;
+				verify_result(args[0].atanh(), "atanh: argument must be between -1 and 1, exclusive")?
+			},
+			FunctionType::BinomPmf => {
+				require_fixed_args(args.len(), 3, "binompmf")?;
+				let k = require_natural(args[0], "binompmf")?;
+				let n = require_natural(args[1], "binompmf")?;
+				let p = args[2];
+				
+				if !(0.0..=1.0).contains(&p) {
+					return error("binompmf: probability must be between 0..1");
+				}
+				
+				compute_binom_pmf(k, n, p)
+			},
+			FunctionType::Ceil => {
+				require_fixed_args(args.len(), 1, "ceil")?;
+				args[0].ceil()
+			},
+			FunctionType::CeilTo => {
+				require_fixed_args(args.len(), 2, "ceilto")?;
+				compute_round_to(args[0], args[1], f64::ceil, "ceilto")?
+			},
+			FunctionType::Choose => {
+				require_min_args(args.len(), 1, "choose")?;
+				args[context.random.below(args.len())]
+			},
+			FunctionType::Clamp => {
+				require_fixed_args(args.len(), 3, "clamp")?;
+				
+				// Written so that a NaN bound is rejected too, as f64::clamp()
+				// would panic on it.
+				if args[1].partial_cmp(&args[2]).is_none_or(|order| order.is_gt()) {
+					return error("clamp: lower bound must not be greater than upper bound");
+				}
+				
+				args[0].clamp(args[1], args[2])
 			},
 			FunctionType::Cos => {
 				require_fixed_args(args.len(), 1, "cos")?;
-				args[0].cos()
+				context.angle_to_radians(args[0]).cos()
+			},
+			FunctionType::CosDeg => {
+				require_fixed_args(args.len(), 1, "cosd")?;
+				args[0].to_radians().cos()
+			},
+			FunctionType::CosTurn => {
+				require_fixed_args(args.len(), 1, "costurn")?;
+				(args[0] * TAU).cos()
+			},
+			FunctionType::Cosh => {
+				require_fixed_args(args.len(), 1, "cosh")?;
+				verify_result(args[0].cosh(), "cosh: overflow")?
+			},
+			FunctionType::Deg => {
+				require_fixed_args(args.len(), 1, "deg")?;
+				verify_result(args[0].to_degrees(), "deg: overflow")?
 			},
 			FunctionType::Exp => {
 				require_fixed_args(args.len(), 1, "exp")?;
 				verify_result(args[0].exp(), "exp: overflow")?
 			},
+			FunctionType::ExpM1 => {
+				require_fixed_args(args.len(), 1, "expm1")?;
+				verify_result(args[0].exp_m1(), "expm1: overflow")?
+			},
+			FunctionType::Fact => {
+				require_fixed_args(args.len(), 1, "fact")?;
+				let n = require_natural(args[0], "fact")?;
+				
+				// Anything past 170! overflows.
+				if n > 170 {
+					return error("fact: overflow");
+				}
+				
+				(2..=n).map(|i| i as f64).product()
+			},
+			FunctionType::Floor => {
+				require_fixed_args(args.len(), 1, "floor")?;
+				args[0].floor()
+			},
+			FunctionType::FloorTo => {
+				require_fixed_args(args.len(), 2, "floorto")?;
+				compute_round_to(args[0], args[1], f64::floor, "floorto")?
+			},
+			FunctionType::Gcd => {
+				require_min_args(args.len(), 2, "gcd")?;
+				let mut result = require_integer(args[0], "gcd")?;
+				
+				for a in &args[1..] {
+					result = compute_gcd(result, require_integer(*a, "gcd")?);
+				}
+				
+				result
+			},
+			FunctionType::GetEnv => {
+				// Parsed into an expression of its own.
+				panic!("FunctionExpression::evaluate(): logic error.")
+			},
+			FunctionType::If => {
+				// Handled before the arguments are evaluated.
+				panic!("FunctionExpression::evaluate(): logic error.")
+			},
+			FunctionType::Integ => {
+				// Parsed into an expression of its own.
+				panic!("FunctionExpression::evaluate(): logic error.")
+			},
+			FunctionType::InvLerp => {
+				require_fixed_args(args.len(), 3, "invlerp")?;
+				
+				if args[0] == args[1] {
+					return error("invlerp: a and b must differ");
+				}
+				
+				verify_result((args[2] - args[0]) / (args[1] - args[0]), "invlerp: the result is undefined")?
+			},
+			FunctionType::IsPrime => {
+				require_fixed_args(args.len(), 1, "isprime")?;
+				require_integer(args[0], "isprime")?;
+				
+				if args[0] >= 2.0 && is_prime(require_exact_natural(args[0], "isprime")?) {
+					1.0
+				} else {
+					0.0
+				}
+			},
+			FunctionType::KthLargest => {
+				require_min_args(args.len(), 2, "kthlargest")?;
+				compute_kth(args[0], &args[1..], true, "kthlargest")?
+			},
+			FunctionType::KthSmallest => {
+				require_min_args(args.len(), 2, "kthsmallest")?;
+				compute_kth(args[0], &args[1..], false, "kthsmallest")?
+			},
+			FunctionType::Lcm => {
+				require_min_args(args.len(), 2, "lcm")?;
+				let mut result = require_integer(args[0], "lcm")?;
+				
+				for a in &args[1..] {
+					result = verify_result(compute_lcm(result, require_integer(*a, "lcm")?), "lcm: overflow")?;
+				}
+				
+				result
+			},
+			FunctionType::Lerp => {
+				require_fixed_args(args.len(), 3, "lerp")?;
+				verify_result(args[0] + (args[1] - args[0]) * args[2], "lerp: the result is undefined")?
+			},
 			FunctionType::Ln => {
 				require_fixed_args(args.len(), 1, "ln")?;
 				verify_result(args[0].ln(), "ln: argument must be greater than zero")?
 			},
+			FunctionType::Ln1P => {
+				require_fixed_args(args.len(), 1, "ln1p")?;
+				
+				if args[0] <= -1.0 {
+					return error("ln1p: argument must be greater than -1");
+				}
+				
+				args[0].ln_1p()
+			},
 			FunctionType::Log => {
-				require_fixed_args(args.len(), 1, "log")?;
-				verify_result(args[0].log10(), "log: argument must be greater than zero")?
+				require_range_args(args.len(), 1, 2, "log")?;
+				
+				if args[0] <= 0.0 {
+					return error("log: argument must be greater than zero");
+				} else if args.len() == 1 {
+					args[0].log10()
+				} else {
+					verify_result(args[0].log(args[1]), "log: base must be positive and other than 1")?
+				}
+			},
+			FunctionType::Log2 => {
+				require_fixed_args(args.len(), 1, "log2")?;
+				verify_result(args[0].log2(), "log2: argument must be greater than zero")?
 			},
 			FunctionType::Max => {
 				require_min_args(args.len(), 2, "max")?;
 				compute_max(args)
 			},
+			FunctionType::Mean => {
+				require_min_args(args.len(), 1, "mean")?;
+				verify_result(compute_mean(&args), "mean: overflow")?
+			},
 			FunctionType::Min => {
 				require_min_args(args.len(), 2, "min")?;
 				compute_min(args)
 			},
+			FunctionType::Mod => {
+				require_fixed_args(args.len(), 2, "mod")?;
+				// The Euclidean remainder, which is never negative, so that e.g.
+				// mod(-1, 24) is 23 as on a clock.
+				verify_result(args[0].rem_euclid(args[1]), "mod: division by zero")?
+			},
+			FunctionType::Ncr => {
+				require_fixed_args(args.len(), 2, "ncr")?;
+				let n = require_natural(args[0], "ncr")?;
+				let k = require_natural(args[1], "ncr")?;
+				
+				if k > n {
+					return error("ncr: k must not be greater than n");
+				}
+				
+				verify_result(compute_combinations(n, k), "ncr: overflow")?
+			},
+			FunctionType::NextPrime => {
+				require_fixed_args(args.len(), 1, "nextprime")?;
+				require_integer(args[0], "nextprime")?;
+				let mut candidate = require_exact_natural(args[0].max(1.0), "nextprime")? + 1;
+				
+				while !is_prime(candidate) {
+					candidate += 1;
+				}
+				
+				if candidate >= MAX_EXACT_INTEGER {
+					return error("nextprime: the next prime is too large to be exact");
+				}
+				
+				candidate as f64
+			},
+			FunctionType::Npr => {
+				require_fixed_args(args.len(), 2, "npr")?;
+				let n = require_natural(args[0], "npr")?;
+				let k = require_natural(args[1], "npr")?;
+				
+				if k > n {
+					return error("npr: k must not be greater than n");
+				}
+				
+				verify_result(compute_permutations(n, k), "npr: overflow")?
+			},
+			FunctionType::Percentile => {
+				require_min_args(args.len(), 2, "percentile")?;
+				compute_percentile(args[0], &args[1..])?
+			},
+			FunctionType::PoissonPmf => {
+				require_fixed_args(args.len(), 2, "poissonpmf")?;
+				let k = require_natural(args[0], "poissonpmf")?;
+				let lambda = args[1];
+				
+				if lambda <= 0.0 {
+					return error("poissonpmf: rate must be greater than zero");
+				}
+				
+				compute_poisson_pmf(k, lambda)
+			},
 			FunctionType::Pow => {
 				require_fixed_args(args.len(), 2, "pow")?;
-				verify_result(args[0].powf(args[1]), "pow: the result is undefined")?
+				verify_result(compute_power(args[0], args[1]), "pow: the result is undefined")?
+			},
+			FunctionType::PowMod => {
+				require_fixed_args(args.len(), 3, "powmod")?;
+				let base = require_natural(args[0], "powmod")?;
+				let exponent = require_natural(args[1], "powmod")?;
+				let modulus = require_natural(args[2], "powmod")?;
+				
+				if modulus == 0 {
+					return error("powmod: modulus must be greater than zero");
+				}
+				
+				compute_powmod(base, exponent, modulus) as f64
+			},
+			FunctionType::Product => {
+				require_min_args(args.len(), 1, "product")?;
+				verify_result(compute_product(&args), "product: overflow")?
+			},
+			FunctionType::QuadRoot1 => {
+				require_fixed_args(args.len(), 3, "quadroot1")?;
+				compute_quadroot(&args, 1.0, "quadroot1")?
+			},
+			FunctionType::QuadRoot2 => {
+				require_fixed_args(args.len(), 3, "quadroot2")?;
+				compute_quadroot(&args, -1.0, "quadroot2")?
+			},
+			FunctionType::Rad => {
+				require_fixed_args(args.len(), 1, "rad")?;
+				args[0].to_radians()
+			},
+			FunctionType::Reduce => {
+				// Parsed into an expression of its own.
+				panic!("FunctionExpression::evaluate(): logic error.")
+			},
+			FunctionType::Root => {
+				require_fixed_args(args.len(), 2, "root")?;
+				compute_root(args[0], args[1])?
+			},
+			FunctionType::Round => {
+				require_fixed_args(args.len(), 1, "round")?;
+				compute_round(args[0], context.round_mode)
+			},
+			FunctionType::Sign => {
+				require_fixed_args(args.len(), 1, "sign")?;
+				
+				// Unlike f64::signum, both zeros have sign 0. NaN can't get here, as
+				// undefined results are caught before they can be used as arguments.
+				if args[0] == 0.0 {
+					0.0
+				} else {
+					args[0].signum()
+				}
 			},
 			FunctionType::Sin => {
 				require_fixed_args(args.len(), 1, "sin")?;
-				args[0].sin()
+				context.angle_to_radians(args[0]).sin()
+			},
+			FunctionType::SinDeg => {
+				require_fixed_args(args.len(), 1, "sind")?;
+				args[0].to_radians().sin()
+			},
+			FunctionType::SinTurn => {
+				require_fixed_args(args.len(), 1, "sinturn")?;
+				(args[0] * TAU).sin()
+			},
+			FunctionType::Sinh => {
+				require_fixed_args(args.len(), 1, "sinh")?;
+				verify_result(args[0].sinh(), "sinh: overflow")?
 			},
 			FunctionType::Sqrt => {
 				require_fixed_args(args.len(), 1, "sqrt")?;
 				verify_result(args[0].sqrt(), "sqrt: argument must be nonnegative")?
 			},
+			FunctionType::Sum => {
+				require_min_args(args.len(), 1, "sum")?;
+				verify_result(compute_sum(&args), "sum: overflow")?
+			},
 			FunctionType::Tan => {
 				require_fixed_args(args.len(), 1, "tan")?;
-				verify_result(args[0].tan(), "tan: result is undefined")?
+				verify_result(context.angle_to_radians(args[0]).tan(), "tan: result is undefined")?
+			},
+			FunctionType::TanDeg => {
+				require_fixed_args(args.len(), 1, "tand")?;
+				verify_result(args[0].to_radians().tan(), "tand: result is undefined")?
+			},
+			FunctionType::TanTurn => {
+				require_fixed_args(args.len(), 1, "tanturn")?;
+				verify_result((args[0] * TAU).tan(), "tanturn: result is undefined")?
+			},
+			FunctionType::Tanh => {
+				require_fixed_args(args.len(), 1, "tanh")?;
+				args[0].tanh()
+			},
+			FunctionType::Trunc => {
+				require_fixed_args(args.len(), 1, "trunc")?;
+				args[0].trunc()
 			}
-		})
+		};
+		
+		// An infinite argument, as in sin(inf), can make the result NaN,
+		// which must not get any further: it would compare false with
+		// everything and be ignored by max() and min().
+		if result.is_nan() {
+			return error(&format!("{}: result is undefined", self.func));
+		}
+		
+		Ok(result)
     }
 }
 
 impl Evaluable for VariableExpression {
 
-    fn evaluate(&self, variables: &HashMap<char, f64>) -> EvaluationResult {
-		if let Some(val) = variables.get(&self.var).copied() {
+    fn evaluate(&self, context: &mut Context) -> EvaluationResult {
+		if let Some(val) = context.read(&self.var) {
 			Ok(val)
 		} else {
 			error(&format!("variable {} is undefined", self.var))
@@ -138,33 +676,498 @@ impl Evaluable for VariableExpression {
 	}
 }
 
+impl Evaluable for ConstantExpression {
+	fn evaluate(&self, _: &mut Context) -> EvaluationResult {
+		Ok(match self.constant {
+			ConstantType::E => std::f64::consts::E,
+			ConstantType::Eps => f64::EPSILON,
+			ConstantType::Inf => f64::INFINITY,
+			ConstantType::MaxFloat => f64::MAX,
+			ConstantType::MinFloat => f64::MIN_POSITIVE,
+			ConstantType::Phi => (1.0 + 5.0_f64.sqrt()) / 2.0,
+			ConstantType::Pi => std::f64::consts::PI,
+			ConstantType::Tau => std::f64::consts::TAU
+		})
+	}
+}
+
+impl Evaluable for LabelExpression {
+	fn evaluate(&self, context: &mut Context) -> EvaluationResult {
+		if let Some(val) = context.labels.get(&self.label).copied() {
+			Ok(val)
+		} else {
+			error(&format!("label {} is undefined", self.label))
+		}
+	}
+}
+
 impl Evaluable for LiteralExpression {
-    fn evaluate(&self, _: &HashMap<char, f64>) -> EvaluationResult {
+    fn evaluate(&self, _: &mut Context) -> EvaluationResult {
         Ok(self.val)
     }
 }
 
+// The most values reduce evaluates its expression for, so that a tiny step
+// cannot keep it running practically forever.
+const MAX_REDUCE_VALUES: f64 = 1e6;
+
+impl Evaluable for ReduceExpression {
+	fn evaluate(&self, context: &mut Context) -> EvaluationResult {
+		let lo = self.lo.evaluate_finite(context)?;
+		let hi = self.hi.evaluate_finite(context)?;
+		let step = self.step.evaluate_finite(context)?;
+		
+		if step <= 0.0 {
+			return error("reduce: the step must be positive");
+		}
+		
+		// Allow for rounding, so that 0 to 1 in steps of 0.1 includes 1.
+		let count = ((hi - lo) / step + 1e-9).floor() + 1.0;
+		
+		if count > MAX_REDUCE_VALUES {
+			return error("reduce: too many values; use a larger step");
+		}
+		
+		let count = count.max(0.0) as usize;
+		
+		if count == 0 && (self.op == ReduceOp::Max || self.op == ReduceOp::Min) {
+			return error(&format!("reduce: no values for {}", self.op));
+		}
+		
+		let values = context.evaluate_bound(&self.var, (0..count).map(|k| lo + k as f64 * step), &self.body)?;
+		
+		match self.op {
+			ReduceOp::Sum => verify_result(compute_sum(&values), "arithmetic overflow during addition"),
+			ReduceOp::Product => verify_result(compute_product(&values), "arithmetic overflow during multiplication"),
+			ReduceOp::Max => Ok(compute_max(values)),
+			ReduceOp::Min => Ok(compute_min(values))
+		}
+	}
+}
+
+impl Evaluable for EnvExpression {
+	fn evaluate(&self, _: &mut Context) -> EvaluationResult {
+		let Ok(value) = env::var(&self.name) else {
+			return error(&format!("getenv: {} is not set", self.name));
+		};
+		
+		match value.trim().parse::<f64>() {
+			Ok(number) => verify_result(number, &format!("getenv: {} is not finite", self.name)),
+			Err(_) => error(&format!("getenv: {} is not a number: {}", self.name, value))
+		}
+	}
+}
+
+impl Evaluable for CommandValueExpression {
+	fn evaluate(&self, context: &mut Context) -> EvaluationResult {
+		match self.command {
+			CommandType::Count => Ok(context.variables.len() as f64),
+			CommandType::NumSteps => Ok(context.steps as f64),
+			_ => panic!("CommandValueExpression::evaluate(): logic error.")
+		}
+	}
+}
+
+impl Evaluable for IntegralExpression {
+	// Simpson's rule on each subinterval, which is exact for polynomials up
+	// to the third degree.
+	fn evaluate(&self, context: &mut Context) -> EvaluationResult {
+		let lo = self.lo.evaluate_finite(context)?;
+		let hi = self.hi.evaluate_finite(context)?;
+		let steps = context.steps;
+		let h = (hi - lo) / steps as f64;
+		
+		// The ends of the subintervals and their midpoints, in order.
+		let points = (0..=2 * steps).map(|k| lo + k as f64 * h / 2.0);
+		let values = context.evaluate_bound(&self.var, points, &self.body)?;
+		
+		let weighted: f64 = values.iter().enumerate().map(|(k, value)| {
+			let weight = if k == 0 || k == 2 * steps { 1.0 } else if k % 2 == 1 { 4.0 } else { 2.0 };
+			weight * value
+		}).sum();
+		
+		verify_result(weighted * h / 6.0, "integ: the integral is undefined")
+	}
+}
+
 mod utility {
 	use crate::errors::Error;
+	use std::f64::consts::TAU;
+	use super::RoundMode;
 	
+	// f64::min() and f64::max() silently ignore a NaN operand, but these never
+	// see one: no function result or operation is NaN without failing, so
+	// e.g. max(sqrt(-1), 2) and max(sin(inf), 2) are errors rather than 2.
 	pub fn compute_min(args: Vec<f64>) -> f64 {
 		let mut result = args[0];
 		
 		for a in &args[1..] {
 			result = result.min(*a);
 		}
-		
-		result
+		
+		result
+	}
+	
+	pub fn compute_max(args: Vec<f64>) -> f64 {
+		let mut result = args[0];
+		
+		for a in &args[1..] {
+			result = result.max(*a);
+		}
+		
+		result
+	}
+	
+	// Like powf(), which is NaN for any negative base with a fractional
+	// exponent, but when the exponent is a ratio p/q with an odd q, there
+	// is a real root whose sign depends on whether p is odd: (-8)^(1/3)
+	// is -2 and (-8)^(2/3) is 4. As 1/3 etc. aren't exact in floating
+	// point, an exponent within 1e-9 of p/q counts as such, for q < 100.
+	pub fn compute_power(base: f64, exponent: f64) -> f64 {
+		if base >= 0.0 || exponent.fract() == 0.0 || !exponent.is_finite() {
+			return base.powf(exponent);
+		}
+		
+		for q in (3..100).step_by(2) {
+			let p = exponent * q as f64;
+			
+			if (p - p.round()).abs() < 1e-9 {
+				let magnitude = (-base).powf(exponent);
+				return if p.round() % 2.0 == 0.0 { magnitude } else { -magnitude };
+			}
+		}
+		
+		f64::NAN
+	}
+	
+	// The n'th root of x, for any nonzero real n. A negative x has a real
+	// root when n is an odd integer, or when 1/n is a fraction with an odd
+	// denominator as in compute_power(), and none otherwise.
+	pub fn compute_root(x: f64, n: f64) -> Result<f64, Error> {
+		if n == 0.0 {
+			return error("root: the zeroth root is undefined");
+		} else if x < 0.0 && n.fract() == 0.0 && n % 2.0 != 0.0 {
+			return Ok(-(-x).powf(1.0 / n));
+		}
+		
+		verify_result(compute_power(x, 1.0 / n), "root: no real root")
+	}
+	
+	// Multiply the terms one at a time, dividing as we go for combinations,
+	// so that moderate results don't overflow on the way like factorials
+	// would. Return infinity on overflow; the loops stop early when that
+	// happens so that huge arguments don't take forever.
+	pub fn compute_combinations(n: u64, k: u64) -> f64 {
+		let k = k.min(n - k);
+		let mut result = 1.0;
+		
+		for i in 1..=k {
+			result = result * (n - k + i) as f64 / i as f64;
+			
+			if result.is_infinite() {
+				break;
+			}
+		}
+		
+		result.round()
+	}
+	
+	pub fn compute_permutations(n: u64, k: u64) -> f64 {
+		let mut result = 1.0;
+		
+		for i in (n - k + 1)..=n {
+			result *= i as f64;
+			
+			if result.is_infinite() {
+				break;
+			}
+		}
+		
+		result
+	}
+	
+	// Euclid's algorithm. The arguments are integers, for which f64
+	// remainders are exact.
+	pub fn compute_gcd(a: f64, b: f64) -> f64 {
+		let (mut a, mut b) = (a.abs(), b.abs());
+		
+		while b != 0.0 {
+			(a, b) = (b, a % b);
+		}
+		
+		a
+	}
+	
+	pub fn compute_lcm(a: f64, b: f64) -> f64 {
+		if a == 0.0 || b == 0.0 {
+			0.0
+		} else {
+			(a / compute_gcd(a, b) * b).abs()
+		}
+	}
+	
+	// Round x to a multiple of m, with rounding up or down given as f64::ceil
+	// or f64::floor.
+	pub fn compute_round_to(x: f64, m: f64, round: fn(f64) -> f64, func: &str) -> Result<f64, Error> {
+		if m == 0.0 {
+			return error(&format!("{}: the multiple must not be zero", func));
+		}
+		
+		verify_result(round(x / m) * m, &format!("{}: overflow", func))
+	}
+	
+	// A root of a x^2 + b x + c = 0, the one with +sqrt(d) when sign is 1
+	// and the one with -sqrt(d) when sign is -1.
+	pub fn compute_quadroot(args: &[f64], sign: f64, func: &str) -> Result<f64, Error> {
+		let (a, b, c) = (args[0], args[1], args[2]);
+		
+		if a == 0.0 {
+			return error(&format!("{}: a must not be zero", func));
+		}
+		
+		let discriminant = verify_result(b * b - 4.0 * a * c, &format!("{}: overflow", func))?;
+		
+		if discriminant < 0.0 {
+			return error(&format!("{}: the roots are not real", func));
+		}
+		
+		verify_result((-b + sign * discriminant.sqrt()) / (2.0 * a), &format!("{}: overflow", func))
+	}
+	
+	// Comparisons are 1 when true and 0 when false, as if() expects.
+	pub fn truth(condition: bool) -> f64 {
+		if condition { 1.0 } else { 0.0 }
+	}
+	
+	pub fn compute_round(x: f64, mode: RoundMode) -> f64 {
+		match mode {
+			RoundMode::HalfAway => x.round(),
+			RoundMode::HalfEven => x.round_ties_even(),
+			RoundMode::HalfUp => {
+				let below = x.floor();
+				if x - below >= 0.5 { below + 1.0 } else { below }
+			},
+			RoundMode::HalfDown => {
+				let above = x.ceil();
+				if above - x >= 0.5 { above - 1.0 } else { above }
+			},
+			RoundMode::Ceil => x.ceil(),
+			RoundMode::Floor => x.floor(),
+			RoundMode::Trunc => x.trunc()
+		}
+	}
+	
+	pub fn compute_sum(args: &[f64]) -> f64 {
+		args.iter().sum()
+	}
+	
+	pub fn compute_product(args: &[f64]) -> f64 {
+		args.iter().product()
+	}
+	
+	pub fn compute_mean(args: &[f64]) -> f64 {
+		compute_sum(args) / args.len() as f64
+	}
+	
+	// The p'th percentile of the data, interpolating linearly between the
+	// two nearest values: the value at rank p / 100 * (n - 1) in sorted order.
+	pub fn compute_percentile(p: f64, data: &[f64]) -> Result<f64, Error> {
+		if !(0.0..=100.0).contains(&p) {
+			return error("percentile must be between 0 and 100");
+		}
+		
+		let mut sorted = data.to_vec();
+		sorted.sort_by(f64::total_cmp);
+		let rank = p / 100.0 * (sorted.len() - 1) as f64;
+		let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+		Ok(sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64))
+	}
+	
+	// The k'th smallest (or largest) of the data, with k counting from 1.
+	pub fn compute_kth(k: f64, data: &[f64], largest: bool, func_name: &str) -> Result<f64, Error> {
+		let k = require_natural(k, func_name)? as usize;
+		
+		if k == 0 || k > data.len() {
+			return error(&format!("{}: k must be between 1 and {}, got {}", func_name, data.len(), k));
+		}
+		
+		let mut sorted = data.to_vec();
+		sorted.sort_by(f64::total_cmp);
+		Ok(if largest { sorted[sorted.len() - k] } else { sorted[k - 1] })
+	}
+	
+	// Below this, binompmf and poissonpmf take the factorials factor by factor.
+	// From it on, they use Stirling's series instead, which is accurate to
+	// double precision there and takes no longer for huge arguments.
+	const STIRLING_MIN: u64 = 20;
+	
+	// Probabilities are computed in log space so that large binomial
+	// coefficients and powers of small probabilities don't overflow or
+	// underflow on the way to a moderate result.
+	pub fn compute_binom_pmf(k: u64, n: u64, p: f64) -> f64 {
+		if k > n {
+			0.0
+		} else if p == 0.0 || p == 1.0 {
+			// ln(0) is not usable; the outcome is certain.
+			let certain_k = if p == 0.0 { 0 } else { n };
+			if k == certain_k { 1.0 } else { 0.0 }
+		} else if k.min(n - k) < STIRLING_MIN {
+			(ln_choose(n, k) + k as f64 * p.ln() + (n - k) as f64 * (-p).ln_1p()).exp()
+		} else {
+			// The terms of the series for the three factorials that would
+			// cancel out with the powers are combined into deviances first.
+			let (k, n) = (k as f64, n as f64);
+			(-deviance(k, n * p) - deviance(n - k, n * (1.0 - p)) + 0.5 * (n / (TAU * k * (n - k))).ln()
+				+ stirling_correction(n) - stirling_correction(k) - stirling_correction(n - k)).exp()
+		}
+	}
+	
+	pub fn compute_poisson_pmf(k: u64, lambda: f64) -> f64 {
+		if k < STIRLING_MIN {
+			(k as f64 * lambda.ln() - lambda - ln_factorial(k)).exp()
+		} else {
+			let k = k as f64;
+			(-deviance(k, lambda) - 0.5 * (TAU * k).ln() - stirling_correction(k)).exp()
+		}
+	}
+	
+	// ln(n! / (k! (n - k)!)) for k <= n.
+	pub fn ln_choose(n: u64, k: u64) -> f64 {
+		let k = k.min(n - k);
+		(1..=k).map(|i| ((n - k + i) as f64 / i as f64).ln()).sum()
+	}
+	
+	// ln(n!)
+	pub fn ln_factorial(n: u64) -> f64 {
+		(2..=n).map(|i| (i as f64).ln()).sum()
+	}
+	
+	// The terms of Stirling's series for ln(n!) after n ln(n) - n + ln(2 pi n) / 2.
+	fn stirling_correction(n: f64) -> f64 {
+		1.0 / (12.0 * n) - 1.0 / (360.0 * n.powi(3)) + 1.0 / (1260.0 * n.powi(5))
+	}
+	
+	// x ln(x / m) + m - x. Near x = m, the direct form loses all its digits
+	// to cancellation, so a series in (x - m) / (x + m) is summed instead.
+	fn deviance(x: f64, m: f64) -> f64 {
+		if (x - m).abs() >= 0.1 * (x + m) {
+			return x * (x / m).ln() + m - x;
+		}
+		
+		let v = (x - m) / (x + m);
+		let mut sum = (x - m) * v;
+		let mut term = 2.0 * x * v;
+		
+		for j in 1.. {
+			term *= v * v;
+			let next = sum + term / (2 * j + 1) as f64;
+			
+			if next == sum {
+				break;
+			}
+			
+			sum = next;
+		}
+		
+		sum
+	}
+	
+	// Computes base^exponent mod modulus by repeated squaring, in 128 bits
+	// so that the intermediate products cannot overflow.
+	pub fn compute_powmod(base: u64, exponent: u64, modulus: u64) -> u64 {
+		let modulus = modulus as u128;
+		let mut base = base as u128 % modulus;
+		let mut exponent = exponent;
+		let mut result = 1 % modulus;
+		
+		while exponent > 0 {
+			if exponent & 1 == 1 {
+				result = result * base % modulus;
+			}
+			
+			base = base * base % modulus;
+			exponent >>= 1;
+		}
+		
+		result as u64
+	}
+	
+	// Deterministic Miller-Rabin test. Testing against the first twelve primes
+	// as witnesses is known to be sufficient for all 64-bit numbers.
+	pub fn is_prime(n: u64) -> bool {
+		let witnesses = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+		
+		if n < 2 {
+			return false;
+		}
+		
+		for p in witnesses {
+			if n.is_multiple_of(p) {
+				return n == p;
+			}
+		}
+		
+		let mut d = n - 1;
+		let mut s = 0;
+		
+		while d.is_multiple_of(2) {
+			d /= 2;
+			s += 1;
+		}
+		
+		'witness: for a in witnesses {
+			let mut x = compute_powmod(a, d, n);
+			
+			if x == 1 || x == n - 1 {
+				continue;
+			}
+			
+			for _ in 1..s {
+				x = compute_powmod(x, 2, n);
+				
+				if x == n - 1 {
+					continue 'witness;
+				}
+			}
+			
+			return false;
+		}
+		
+		true
+	}
+	
+	// Returns Err unless the value is an integer.
+	pub fn require_integer(value: f64, func_name: &str) -> Result<f64, Error> {
+		if value.fract() == 0.0 {
+			Ok(value)
+		} else {
+			error(&format!("{}: argument must be an integer, got {}", func_name, value))
+		}
 	}
 	
-	pub fn compute_max(args: Vec<f64>) -> f64 {
-		let mut result = args[0];
-		
-		for a in &args[1..] {
-			result = result.max(*a);
+	// Returns Err unless the value is a nonnegative integer that fits in an u64.
+	pub fn require_natural(value: f64, func_name: &str) -> Result<u64, Error> {
+		if value.fract() == 0.0 && value >= 0.0 && value < u64::MAX as f64 {
+			Ok(value as u64)
+		} else {
+			error(&format!("{}: arguments must be nonnegative integers, got {}", func_name, value))
 		}
+	}
+	
+	// From 2^53 on, an f64 can no longer hold every integer: 2^53 + 1 reads
+	// as 2^53, for example.
+	pub const MAX_EXACT_INTEGER: u64 = 1 << 53;
+	
+	// Returns Err unless the value is a nonnegative integer below
+	// MAX_EXACT_INTEGER, for functions whose answer depends on every digit.
+	pub fn require_exact_natural(value: f64, func_name: &str) -> Result<u64, Error> {
+		let n = require_natural(value, func_name)?;
 		
-		result
+		if n < MAX_EXACT_INTEGER {
+			Ok(n)
+		} else {
+			error(&format!("{}: arguments must be less than 2^53 to be exact, got {}", func_name, value))
+		}
 	}
 	
 	// Returns Err if the number of args is incorrect. The returned Ok() value is unusable.
@@ -187,6 +1190,15 @@ mod utility {
 		}
 	}
 	
+	// Returns Err if the number of args is outside the given range. The returned Ok() value is unusable.
+	pub fn require_range_args(args_size: usize, min_size: usize, max_size: usize, func_name: &str) -> Result<f64, Error> {
+		if (min_size..=max_size).contains(&args_size) {
+			Ok(0.0)
+		} else {
+			error(&format!("{}: {} to {} arguments required, got {}", func_name, min_size, max_size, args_size))
+		}
+	}
+	
 	pub fn verify_result(result: f64, on_failure: &str) -> Result<f64, Error> {
 		if result.is_finite() {
 			Ok(result)
@@ -195,7 +1207,7 @@ mod utility {
 		}
 	}
 	
-	pub fn error(description: &str) -> Result<f64, Error> {
+	pub fn error<T>(description: &str) -> Result<T, Error> {
 		Err(Error::new(&format!("evaluation error: {}.", description)))
 	}
 }
@@ -203,7 +1215,8 @@ mod utility {
 #[cfg(test)]
 mod tests {
 	use super::*;
-
+	use assert_approx_eq::assert_approx_eq;
+	
 	#[test]
 	fn test_addition() {
 		assert_approx_eq!(run_single_expression("2 + 6"), 8.0);
@@ -234,19 +1247,94 @@ mod tests {
 		expect_expression_to_fail("-2 / 0");
 	}
 	
+	#[test]
+	fn test_floor_division() {
+		assert_eq!(run_single_expression("7 // 2"), 3.0);
+		assert_eq!(run_single_expression("-7 // 2"), -4.0);
+		assert_eq!(run_single_expression("7.5 // -2"), -4.0);
+		assert_eq!(run_single_expression("2 * 7 // 4"), 3.0);
+		expect_expression_to_fail("5 // 0");
+		assert!(Parser::new("5 //").parse().is_err());
+	}
+	
+	#[test]
+	fn test_long_chain_of_operators() {
+		let line = vec!["1"; 5000].join("+");
+		assert_eq!(run_single_expression(&line), 5000.0);
+		let line = String::from("100") + &"-1".repeat(5000) + "*2";
+		assert_eq!(run_single_expression(&line), -4901.0);
+	}
+	
+	#[test]
+	fn test_modulo() {
+		assert_approx_eq!(run_single_expression("7 % 3"), 1.0);
+		assert_approx_eq!(run_single_expression("-7 % 3"), 2.0);
+		assert_approx_eq!(run_single_expression("7 % -3"), 1.0);
+		assert_approx_eq!(run_single_expression("7.5 % 2"), 1.5);
+		assert_approx_eq!(run_single_expression("2 * 7 % 4"), 2.0);
+		expect_expression_to_fail("5 % 0");
+	}
+	
 	#[test]
 	fn test_power() {
 		assert_approx_eq!(run_single_expression("3 ^ 4"), 81.0);
 		assert_approx_eq!(run_single_expression("9 ^ .5"), 3.0);
 		assert_approx_eq!(run_single_expression("2^-2"), 0.25);
-		expect_expression_to_fail("-1^0.5");
+		expect_expression_to_fail("(-1)^0.5");
 	}
-
+	
+	#[test]
+	fn test_comparison() {
+		assert_eq!(run_single_expression("1 < 2"), 1.0);
+		assert_eq!(run_single_expression("2 < 1"), 0.0);
+		assert_eq!(run_single_expression("2 <= 2"), 1.0);
+		assert_eq!(run_single_expression("1 > 2"), 0.0);
+		assert_eq!(run_single_expression("-1 >= -1"), 1.0);
+		assert_eq!(run_single_expression("0.1 + 0.2 == 0.3"), 0.0);
+		assert_eq!(run_single_expression("3! != 6"), 0.0);
+		assert_eq!(run_single_expression("if(2^10 > 1000, 5, 6)"), 5.0);
+	}
+	
+	#[test]
+	fn test_unary_minus_binds_looser_than_power() {
+		assert_eq!(run_single_expression("-2^2"), -4.0);
+		assert_eq!(run_single_expression("-2^3"), -8.0);
+		assert_eq!(run_single_expression("(-2)^2"), 4.0);
+		assert_eq!(run_single_expression("-1^0.5"), -1.0);
+	}
+	
+	#[test]
+	fn test_power_of_negative_base_with_rational_exponent() {
+		assert_eq!(run_single_expression("(-8)^(1/3)"), -2.0);
+		assert_approx_eq!(run_single_expression("(-8)^(2/3)"), 4.0);
+		assert_approx_eq!(run_single_expression("(-32)^(-3/5)"), -0.125);
+		assert_approx_eq!(run_single_expression("(-8)^(1/3 + 1e-12)"), -2.0);
+		assert_approx_eq!(run_single_expression("pow(-27, 1/3)"), -3.0);
+		expect_expression_to_fail("(-8)^(1/2)");
+		expect_expression_to_fail("(-8)^(1/6)");
+		expect_expression_to_fail("(-8)^0.3");
+		expect_expression_to_fail("(-8)^(1/3 + 1e-6)");
+	}
+	
 	#[test]
 	fn test_abs() {
 		assert_approx_eq!(run_single_expression("abs(-3)"), 3.0);
 	}
 	
+	#[test]
+	fn test_acosd() {
+		assert_approx_eq!(run_single_expression("acosd(0.5)"), 60.0);
+		assert_approx_eq!(run_single_expression("acosd(-1)"), 180.0);
+		expect_expression_to_fail("acosd(-2)");
+	}
+	
+	#[test]
+	fn test_acosh() {
+		assert_approx_eq!(run_single_expression("acosh(1)"), 0.0);
+		assert_approx_eq!(run_single_expression("acosh(cosh(2))"), 2.0);
+		expect_expression_to_fail("acosh(0.5)");
+	}
+	
 	#[test]
 	fn test_arccos() {
 	}
@@ -259,6 +1347,99 @@ mod tests {
 	fn test_arctan() {
 	}
 	
+	#[test]
+	fn test_asind() {
+		assert_approx_eq!(run_single_expression("asind(0.5)"), 30.0);
+		assert_approx_eq!(run_single_expression("asind(-1)"), -90.0);
+		expect_expression_to_fail("asind(2)");
+	}
+	
+	#[test]
+	fn test_asinh() {
+		assert_approx_eq!(run_single_expression("asinh(0)"), 0.0);
+		assert_approx_eq!(run_single_expression("asinh(sinh(2))"), 2.0);
+	}
+	
+	#[test]
+	fn test_atan2() {
+		assert_approx_eq!(run_single_expression("atan2(1, 1)"), std::f64::consts::FRAC_PI_4);
+		assert_approx_eq!(run_single_expression("atan2(1, -1)"), 3.0 * std::f64::consts::FRAC_PI_4);
+		assert_approx_eq!(run_single_expression("atan2(-1, -1)"), -3.0 * std::f64::consts::FRAC_PI_4);
+		assert_approx_eq!(run_single_expression("atan2(0, 0)"), 0.0);
+		expect_expression_to_fail("atan2(1)");
+	}
+	
+	#[test]
+	fn test_atand() {
+		assert_approx_eq!(run_single_expression("atand(1)"), 45.0);
+		assert_approx_eq!(run_single_expression("atand(inf)"), 90.0);
+	}
+	
+	#[test]
+	fn test_atanh() {
+		assert_approx_eq!(run_single_expression("atanh(0.5)"), 0.5493061443340549);
+		assert_approx_eq!(run_single_expression("atanh(tanh(-2))"), -2.0);
+		expect_expression_to_fail("atanh(1)");
+		expect_expression_to_fail("atanh(-1.5)");
+	}
+	
+	#[test]
+	fn test_binompmf() {
+		assert_approx_eq!(run_single_expression("binompmf(2, 5, 0.5)"), 0.3125);
+		assert_approx_eq!(run_single_expression("binompmf(0, 3, 0.1)"), 0.729);
+		assert_approx_eq!(run_single_expression("binompmf(500, 1000, 0.5)"), 0.0252250181783608);
+		assert_approx_eq!(run_single_expression("binompmf(0, 5, 0)"), 1.0);
+		assert_approx_eq!(run_single_expression("binompmf(5, 5, 1)"), 1.0);
+		assert_approx_eq!(run_single_expression("binompmf(4, 5, 1)"), 0.0);
+		assert_approx_eq!(run_single_expression("binompmf(6, 5, 0.5)"), 0.0);
+		expect_expression_to_fail("binompmf(2, 5, 1.5)");
+		expect_expression_to_fail("binompmf(2, 5, -0.5)");
+		expect_expression_to_fail("binompmf(2.5, 5, 0.5)");
+		expect_expression_to_fail("binompmf(2, -5, 0.5)");
+		// Large arguments take no longer than small ones.
+		assert_approx_eq!(run_single_expression("binompmf(5e9, 1e10, 0.5) * 1e6"), 7.978845607829182);
+	}
+	
+	#[test]
+	fn test_ceil() {
+		assert_approx_eq!(run_single_expression("ceil(1.5)"), 2.0);
+		assert_approx_eq!(run_single_expression("ceil(-1.5)"), -1.0);
+		assert_approx_eq!(run_single_expression("ceil(3)"), 3.0);
+		expect_expression_to_fail("ceil()");
+	}
+	
+	#[test]
+	fn test_ceilto() {
+		assert_approx_eq!(run_single_expression("ceilto(23, 10)"), 30.0);
+		assert_approx_eq!(run_single_expression("ceilto(-23, 10)"), -20.0);
+		assert_approx_eq!(run_single_expression("ceilto(30, 10)"), 30.0);
+		assert_approx_eq!(run_single_expression("ceilto(0.27, 0.1)"), 0.3);
+		expect_expression_to_fail("ceilto(23, 0)");
+		expect_expression_to_fail("ceilto(23)");
+	}
+	
+	#[test]
+	fn test_choose() {
+		for _ in 0..20 {
+			let value = run_single_expression("choose(1, 2, 3)");
+			assert!([1.0, 2.0, 3.0].contains(&value));
+		}
+		
+		assert_approx_eq!(run_single_expression("choose(7)"), 7.0);
+		expect_expression_to_fail("choose()");
+	}
+	
+	#[test]
+	fn test_clamp() {
+		assert_approx_eq!(run_single_expression("clamp(5, 0, 10)"), 5.0);
+		assert_approx_eq!(run_single_expression("clamp(-1, 0, 10)"), 0.0);
+		assert_approx_eq!(run_single_expression("clamp(20, 0, 10)"), 10.0);
+		assert_approx_eq!(run_single_expression("clamp(20, 3, 3)"), 3.0);
+		expect_expression_to_fail("clamp(1, 5, 2)");
+		expect_expression_to_fail("clamp(1, sin(inf), 2)");
+		expect_expression_to_fail("clamp(1, 5)");
+	}
+	
 	#[test]
 	fn test_cos() {
 		assert_approx_eq!(run_single_expression("cos(0.0)"), 1.0);
@@ -267,6 +1448,35 @@ mod tests {
 		expect_expression_to_fail("cos(1.0, 2.0)");
 	}
 	
+	#[test]
+	fn test_cosd() {
+		assert_eq!(run_single_expression("cosd(180)"), -1.0);
+		assert_approx_eq!(run_single_expression("cosd(60)"), 0.5);
+		expect_expression_to_fail("cosd(1, 2)");
+	}
+	
+	#[test]
+	fn test_cosh() {
+		assert_approx_eq!(run_single_expression("cosh(0)"), 1.0);
+		assert_approx_eq!(run_single_expression("cosh(-1)"), (std::f64::consts::E + 1.0 / std::f64::consts::E) / 2.0);
+		expect_expression_to_fail("cosh(1000)");
+	}
+	
+	#[test]
+	fn test_costurn() {
+		assert_approx_eq!(run_single_expression("costurn(0)"), 1.0);
+		assert_approx_eq!(run_single_expression("costurn(0.5)"), -1.0);
+		expect_expression_to_fail("costurn(1, 2)");
+	}
+	
+	#[test]
+	fn test_deg() {
+		assert_approx_eq!(run_single_expression("deg(pi)"), 180.0);
+		assert_approx_eq!(run_single_expression("deg(rad(45))"), 45.0);
+		assert_approx_eq!(run_single_expression("deg(-tau)"), -360.0);
+		expect_expression_to_fail("deg(maxfloat)");
+	}
+	
 	#[test]
 	fn test_exp() {
 		assert_approx_eq!(run_single_expression("exp(2)"), 7.3890561);
@@ -274,6 +1484,168 @@ mod tests {
 		expect_expression_to_fail("exp(1.0, 1.0)");
 	}
 	
+	#[test]
+	fn test_expm1() {
+		assert_approx_eq!(run_single_expression("expm1(0)"), 0.0);
+		assert_approx_eq!(run_single_expression("expm1(1)"), std::f64::consts::E - 1.0);
+		
+		// The naive form loses most of its digits to cancellation.
+		let exact = 1.00000000005e-10;
+		let accurate = run_single_expression("expm1(1e-10)");
+		let naive = run_single_expression("exp(1e-10) - 1");
+		assert!((accurate - exact).abs() / exact < 1e-15);
+		assert!((naive - exact).abs() / exact > 1e-9);
+		expect_expression_to_fail("expm1(1000)");
+	}
+	
+	#[test]
+	fn test_fact() {
+		assert_approx_eq!(run_single_expression("fact(5)"), 120.0);
+		assert_approx_eq!(run_single_expression("5!"), 120.0);
+		assert_approx_eq!(run_single_expression("0!"), 1.0);
+		assert_approx_eq!(run_single_expression("3!^2"), 36.0);
+		assert_approx_eq!(run_single_expression("-3!"), -6.0);
+		assert!(run_single_expression("170!").is_finite());
+		expect_expression_to_fail("(-1)!");
+		expect_expression_to_fail("2.5!");
+		expect_expression_to_fail("171!");
+		expect_expression_to_fail("fact(1e300)");
+	}
+	
+	#[test]
+	fn test_floor() {
+		assert_approx_eq!(run_single_expression("floor(1.5)"), 1.0);
+		assert_approx_eq!(run_single_expression("floor(-1.5)"), -2.0);
+		assert_approx_eq!(run_single_expression("floor(3)"), 3.0);
+		expect_expression_to_fail("floor(1, 2)");
+	}
+	
+	#[test]
+	fn test_floorto() {
+		assert_approx_eq!(run_single_expression("floorto(23, 10)"), 20.0);
+		assert_approx_eq!(run_single_expression("floorto(-23, 10)"), -30.0);
+		assert_approx_eq!(run_single_expression("floorto(0.27, 0.1)"), 0.2);
+		assert_approx_eq!(run_single_expression("floorto(7, 2.5)"), 5.0);
+		expect_expression_to_fail("floorto(23, 0)");
+	}
+	
+	#[test]
+	fn test_gcd() {
+		assert_approx_eq!(run_single_expression("gcd(12, 18)"), 6.0);
+		assert_approx_eq!(run_single_expression("gcd(12, 18, 30)"), 6.0);
+		assert_approx_eq!(run_single_expression("gcd(-12, 18)"), 6.0);
+		assert_approx_eq!(run_single_expression("gcd(0, 5)"), 5.0);
+		assert_approx_eq!(run_single_expression("gcd(7, 13)"), 1.0);
+		expect_expression_to_fail("gcd(2.5, 5)");
+		expect_expression_to_fail("gcd(12)");
+	}
+	
+	#[test]
+	fn test_if() {
+		assert_eq!(run_single_expression("if(1, 5, 9)"), 5.0);
+		assert_eq!(run_single_expression("if(0, 5, 9)"), 9.0);
+		assert_eq!(run_single_expression("if(-0.5, 5, 9)"), 5.0);
+		assert_eq!(run_single_expression("if(1, 2, sqrt(-1))"), 2.0);
+		assert_eq!(run_single_expression("if(0, 1/0, 3)"), 3.0);
+		expect_expression_to_fail("if(0, 2, sqrt(-1))");
+		expect_expression_to_fail("if(1, 2)");
+	}
+	
+	#[test]
+	fn test_getenv() {
+		// SAFETY: no other test uses these variables, and nothing else
+		// reads or changes the environment concurrently.
+		unsafe {
+			env::set_var("CLICALC_TEST_SCALE", "2.5");
+			env::set_var("CLICALC_TEST_WORD", "large");
+			env::remove_var("CLICALC_TEST_UNSET");
+		}
+		
+		assert_approx_eq!(run_single_expression("getenv(\"CLICALC_TEST_SCALE\") * 10"), 25.0);
+		expect_expression_to_fail("getenv(\"CLICALC_TEST_WORD\")");
+		expect_expression_to_fail("getenv(\"CLICALC_TEST_UNSET\")");
+	}
+	
+	#[test]
+	fn test_integ() {
+		assert_approx_eq!(run_single_expression("integ(x, 0, 1, x^2)"), 1.0 / 3.0);
+		assert_approx_eq!(run_single_expression("integ(t, 0, pi, sin(t))"), 2.0);
+		assert_approx_eq!(run_single_expression("integ(x, 1, 0, x)"), -0.5);
+		assert_approx_eq!(run_single_expression("integ(x, 2, 2, x)"), 0.0);
+		assert_approx_eq!(run_single_expression("integ(x, 0, 1, integ(y, 0, x, 1))"), 0.5);
+		expect_expression_to_fail("integ(x, 0, 1, 1/x)");
+		expect_expression_to_fail("integ(x, 0, inf, x)");
+	}
+	
+	#[test]
+	fn test_invlerp() {
+		assert_approx_eq!(run_single_expression("invlerp(0, 10, 5)"), 0.5);
+		assert_approx_eq!(run_single_expression("invlerp(0, 10, 20)"), 2.0);
+		assert_approx_eq!(run_single_expression("invlerp(lerp(3, 7, 0.25), 7, 3)"), -1.0 / 3.0);
+		expect_expression_to_fail("invlerp(2, 2, 2)");
+	}
+	
+	#[test]
+	fn test_isprime() {
+		assert_approx_eq!(run_single_expression("isprime(7)"), 1.0);
+		assert_approx_eq!(run_single_expression("isprime(8)"), 0.0);
+		assert_approx_eq!(run_single_expression("isprime(2)"), 1.0);
+		assert_approx_eq!(run_single_expression("isprime(1)"), 0.0);
+		assert_approx_eq!(run_single_expression("isprime(0)"), 0.0);
+		assert_approx_eq!(run_single_expression("isprime(-7)"), 0.0);
+		assert_approx_eq!(run_single_expression("isprime(561)"), 0.0);
+		assert_approx_eq!(run_single_expression("isprime(1000000007)"), 1.0);
+		assert_approx_eq!(run_single_expression("isprime(4294967297)"), 0.0);
+		expect_expression_to_fail("isprime(7.5)");
+		assert_approx_eq!(run_single_expression("isprime(9007199254740881)"), 1.0);
+		expect_expression_to_fail("isprime(2^53)");
+		expect_expression_to_fail("isprime(1e17)");
+	}
+	
+	#[test]
+	fn test_kthlargest() {
+		assert_eq!(run_single_expression("kthlargest(1, 5, 1, 3)"), 5.0);
+		assert_eq!(run_single_expression("kthlargest(2, 5, 1, 3)"), 3.0);
+		assert_eq!(run_single_expression("kthlargest(3, 5, 1, 3)"), 1.0);
+		assert_eq!(run_single_expression("kthlargest(2, 4, 4, 2)"), 4.0);
+		expect_expression_to_fail("kthlargest(1)");
+		expect_expression_to_fail("kthlargest(0, 5, 1, 3)");
+		expect_expression_to_fail("kthlargest(4, 5, 1, 3)");
+		expect_expression_to_fail("kthlargest(1.5, 5, 1, 3)");
+	}
+	
+	#[test]
+	fn test_kthsmallest() {
+		assert_eq!(run_single_expression("kthsmallest(1, 5, 1, 3)"), 1.0);
+		assert_eq!(run_single_expression("kthsmallest(2, 5, 1, 3)"), 3.0);
+		assert_eq!(run_single_expression("kthsmallest(3, 5, 1, 3)"), 5.0);
+		assert_eq!(run_single_expression("kthsmallest(1, -2)"), -2.0);
+		expect_expression_to_fail("kthsmallest(1)");
+		expect_expression_to_fail("kthsmallest(0, 5, 1, 3)");
+		expect_expression_to_fail("kthsmallest(4, 5, 1, 3)");
+		expect_expression_to_fail("kthsmallest(-1, 5, 1, 3)");
+		expect_expression_to_fail("kthsmallest(2.5, 5, 1, 3)");
+	}
+	
+	#[test]
+	fn test_lcm() {
+		assert_approx_eq!(run_single_expression("lcm(4, 6)"), 12.0);
+		assert_approx_eq!(run_single_expression("lcm(4, 6, 10)"), 60.0);
+		assert_approx_eq!(run_single_expression("lcm(-4, 6)"), 12.0);
+		assert_approx_eq!(run_single_expression("lcm(0, 6)"), 0.0);
+		expect_expression_to_fail("lcm(4, 6.5)");
+		expect_expression_to_fail("lcm(maxfloat, maxfloat - 2^971)");
+	}
+	
+	#[test]
+	fn test_lerp() {
+		assert_approx_eq!(run_single_expression("lerp(0, 10, 0.5)"), 5.0);
+		assert_approx_eq!(run_single_expression("lerp(0, 10, 0)"), 0.0);
+		assert_approx_eq!(run_single_expression("lerp(0, 10, 2)"), 20.0);
+		assert_approx_eq!(run_single_expression("lerp(10, 0, -0.5)"), 15.0);
+		expect_expression_to_fail("lerp(0, 10)");
+	}
+	
 	#[test]
 	fn test_ln() {
 		assert_approx_eq!(run_single_expression("ln(2.718281828)"), 1.0);
@@ -285,15 +1657,48 @@ mod tests {
 		expect_expression_to_fail("ln(1.0, 1.0)");
 	}
 	
+	#[test]
+	fn test_ln1p() {
+		assert_approx_eq!(run_single_expression("ln1p(0)"), 0.0);
+		assert_approx_eq!(run_single_expression("ln1p(e - 1)"), 1.0);
+		
+		let exact = 9.9999999995e-11;
+		assert!((run_single_expression("ln1p(1e-10)") - exact).abs() / exact < 1e-15);
+		expect_expression_to_fail("ln1p(-1)");
+		expect_expression_to_fail("ln1p(-2)");
+	}
+	
 	#[test]
 	fn test_log() {
 		assert_approx_eq!(run_single_expression("log(100)"), 2.0);
 		assert_approx_eq!(run_single_expression("log(0.0100)"), -2.0);
+		assert_approx_eq!(run_single_expression("log(1000)"), 3.0);
+		assert_approx_eq!(run_single_expression("log(8, 2)"), 3.0);
+		assert_approx_eq!(run_single_expression("log(8, 0.5)"), -3.0);
+		assert_approx_eq!(run_single_expression("log(e^2, e)"), 2.0);
 		expect_expression_to_fail("log(14.0, 1.0)");
+		expect_expression_to_fail("log(8, 1)");
+		expect_expression_to_fail("log(8, -2)");
+		expect_expression_to_fail("log(0, 2)");
+		expect_expression_to_fail("log(8, 2, 2)");
+		expect_expression_to_fail("log()");
 		expect_expression_to_fail("log(0.0)");
 		expect_expression_to_fail("log(-10.0)");
 	}
 	
+	#[test]
+	fn test_log2() {
+		assert_approx_eq!(run_single_expression("log2(8)"), 3.0);
+		assert_approx_eq!(run_single_expression("log2(0.5)"), -1.0);
+		
+		// Powers of two give exact results, unlike log(x, 2).
+		assert_eq!(run_single_expression("log2(2^1023)"), 1023.0);
+		assert_eq!(run_single_expression("log2(2^-1074)"), -1074.0);
+		expect_expression_to_fail("log2(0)");
+		expect_expression_to_fail("log2(-8)");
+		expect_expression_to_fail("log2(8, 2)");
+	}
+	
 	#[test]
 	fn test_max() {
 		assert_approx_eq!(run_single_expression("max(0, 2)"), 2.0);
@@ -302,6 +1707,21 @@ mod tests {
 		expect_expression_to_fail("max(1.0)");
 	}
 	
+	#[test]
+	fn test_max_with_undefined_argument() {
+		expect_expression_to_fail("max(sqrt(-1), 2)");
+		expect_expression_to_fail("max(2, ln(0))");
+		expect_expression_to_fail("max(1, 0/0, 3)");
+	}
+	
+	#[test]
+	fn test_mean() {
+		assert_approx_eq!(run_single_expression("mean(2, 4, 6)"), 4.0);
+		assert_approx_eq!(run_single_expression("avg(2, 4, 6)"), 4.0);
+		assert_approx_eq!(run_single_expression("mean(5)"), 5.0);
+		expect_expression_to_fail("mean()");
+	}
+	
 	#[test]
 	fn test_min() {
 		assert_approx_eq!(run_single_expression("min(0, 2)"), 0.0);
@@ -310,6 +1730,85 @@ mod tests {
 		expect_expression_to_fail("min(1.0)");
 	}
 	
+	#[test]
+	fn test_min_with_undefined_argument() {
+		expect_expression_to_fail("min(sqrt(-1), 2)");
+		expect_expression_to_fail("min(2, arcsin(2))");
+	}
+	
+	#[test]
+	fn test_mod() {
+		assert_approx_eq!(run_single_expression("mod(7, 3)"), 1.0);
+		assert_approx_eq!(run_single_expression("mod(-1, 24)"), 23.0);
+		assert_approx_eq!(run_single_expression("mod(-7, -3)"), 2.0);
+		expect_expression_to_fail("mod(5, 0)");
+		expect_expression_to_fail("mod(5)");
+	}
+	
+	#[test]
+	fn test_ncr() {
+		assert_approx_eq!(run_single_expression("ncr(5, 2)"), 10.0);
+		assert_approx_eq!(run_single_expression("ncr(5, 0)"), 1.0);
+		assert_approx_eq!(run_single_expression("ncr(5, 5)"), 1.0);
+		assert_eq!(run_single_expression("ncr(50, 25)"), 126410606437752.0);
+		assert!(run_single_expression("ncr(1000, 500)") > 1e299);
+		expect_expression_to_fail("ncr(3, 5)");
+		expect_expression_to_fail("ncr(-3, 2)");
+		expect_expression_to_fail("ncr(5, 2.5)");
+		expect_expression_to_fail("ncr(2000, 1000)");
+	}
+	
+	#[test]
+	fn test_nextprime() {
+		assert_approx_eq!(run_single_expression("nextprime(7)"), 11.0);
+		assert_approx_eq!(run_single_expression("nextprime(0)"), 2.0);
+		assert_approx_eq!(run_single_expression("nextprime(-10)"), 2.0);
+		assert_approx_eq!(run_single_expression("nextprime(2)"), 3.0);
+		assert_approx_eq!(run_single_expression("nextprime(1000000000)"), 1000000007.0);
+		expect_expression_to_fail("nextprime(2.5)");
+		assert_approx_eq!(run_single_expression("nextprime(9007199254740880)"), 9007199254740881.0);
+		expect_expression_to_fail("nextprime(9007199254740881)");
+		expect_expression_to_fail("nextprime(2^53)");
+		expect_expression_to_fail("nextprime(1e17)");
+	}
+	
+	#[test]
+	fn test_npr() {
+		assert_approx_eq!(run_single_expression("npr(5, 2)"), 20.0);
+		assert_approx_eq!(run_single_expression("npr(5, 0)"), 1.0);
+		assert_approx_eq!(run_single_expression("npr(5, 5)"), 120.0);
+		expect_expression_to_fail("npr(3, 5)");
+		expect_expression_to_fail("npr(5, -1)");
+		expect_expression_to_fail("npr(1e18, 1e17)");
+	}
+	
+	#[test]
+	fn test_percentile() {
+		assert_eq!(run_single_expression("percentile(50, 1, 2, 3)"), 2.0);
+		assert_eq!(run_single_expression("percentile(50, 4, 1, 3, 2)"), 2.5);
+		assert_eq!(run_single_expression("percentile(0, 5, -1, 3)"), -1.0);
+		assert_eq!(run_single_expression("percentile(100, 5, -1, 3)"), 5.0);
+		assert_eq!(run_single_expression("percentile(25, 10, 20, 30, 40, 50)"), 20.0);
+		assert_approx_eq!(run_single_expression("percentile(90, 1, 2)"), 1.9);
+		assert_eq!(run_single_expression("percentile(75, 7)"), 7.0);
+		expect_expression_to_fail("percentile(50)");
+		expect_expression_to_fail("percentile(101, 1, 2)");
+		expect_expression_to_fail("percentile(-1, 1, 2)");
+	}
+	
+	#[test]
+	fn test_poissonpmf() {
+		assert_approx_eq!(run_single_expression("poissonpmf(0, 1)"), run_single_expression("exp(-1)"));
+		assert_approx_eq!(run_single_expression("poissonpmf(3, 2)"), 0.1804470443154836);
+		assert_approx_eq!(run_single_expression("poissonpmf(100, 100)"), 0.039860996809147134);
+		expect_expression_to_fail("poissonpmf(1, 0)");
+		expect_expression_to_fail("poissonpmf(-1, 1)");
+		expect_expression_to_fail("poissonpmf(1.5, 1)");
+		// Large arguments take no longer than small ones.
+		assert_approx_eq!(run_single_expression("poissonpmf(1e10, 1e10) * 1e6"), 3.989422803981082);
+		assert_approx_eq!(run_single_expression("poissonpmf(1e10, 1)"), 0.0);
+	}
+	
 	#[test]
 	fn test_pow() {
 		assert_approx_eq!(run_single_expression("pow(16, 2)"), 256.0);
@@ -318,7 +1817,122 @@ mod tests {
 		expect_expression_to_fail("pow(1.0)");
 		expect_expression_to_fail("pow(0, -1)");
 	}
+	
+	#[test]
+	fn test_powmod() {
+		assert_approx_eq!(run_single_expression("powmod(2, 10, 1000)"), 24.0);
+		assert_approx_eq!(run_single_expression("powmod(7, 0, 5)"), 1.0);
+		assert_approx_eq!(run_single_expression("powmod(7, 0, 1)"), 0.0);
+		assert_approx_eq!(run_single_expression("powmod(3, 200, 1000000007)"), 136318165.0);
+		assert_approx_eq!(run_single_expression("powmod(123456789, 987654321, 4294967291)"), 4114726592.0);
+		expect_expression_to_fail("powmod(2.5, 2, 3)");
+		expect_expression_to_fail("powmod(2, -1, 3)");
+		expect_expression_to_fail("powmod(2, 1, 0)");
+		expect_expression_to_fail("powmod(2, 1, -3)");
+		expect_expression_to_fail("powmod(2, 1)");
+	}
+	
+	#[test]
+	fn test_product() {
+		assert_approx_eq!(run_single_expression("product(2, 3, 4)"), 24.0);
+		assert_approx_eq!(run_single_expression("product(5)"), 5.0);
+		expect_expression_to_fail("product()");
+		expect_expression_to_fail("product(1e200, 1e200)");
+	}
+	
+	#[test]
+	fn test_quadroot1() {
+		assert_approx_eq!(run_single_expression("quadroot1(2, -5, 3)"), 1.5);
+		assert_approx_eq!(run_single_expression("quadroot1(1, -2, 1)"), 1.0);
+		assert_approx_eq!(run_single_expression("quadroot1(-1, 0, 4)"), -2.0);
+		expect_expression_to_fail("quadroot1(0, 2, 1)");
+		expect_expression_to_fail("quadroot1(1, 0, 1)");
+		expect_expression_to_fail("quadroot1(1, 2)");
+	}
+	
+	#[test]
+	fn test_quadroot2() {
+		assert_approx_eq!(run_single_expression("quadroot2(2, -5, 3)"), 1.0);
+		assert_approx_eq!(run_single_expression("quadroot2(1, -2, 1)"), 1.0);
+		assert_approx_eq!(run_single_expression("quadroot2(-1, 0, 4)"), 2.0);
+		expect_expression_to_fail("quadroot2(0, 2, 1)");
+		expect_expression_to_fail("quadroot2(1, 0, 1)");
+	}
+	
+	#[test]
+	fn test_rad() {
+		assert_approx_eq!(run_single_expression("rad(180)"), std::f64::consts::PI);
+		assert_approx_eq!(run_single_expression("sin(rad(30))"), 0.5);
+		expect_expression_to_fail("rad(1, 2)");
+	}
+	
+	#[test]
+	fn test_reduce() {
+		assert_approx_eq!(run_single_expression("reduce(+, i, 1, 5, 1, i)"), 15.0);
+		assert_approx_eq!(run_single_expression("reduce(max, i, 1, 5, 1, i^2)"), 25.0);
+		assert_approx_eq!(run_single_expression("reduce(min, x, -1, 1, 0.5, x^2 - x)"), -0.25);
+		assert_approx_eq!(run_single_expression("reduce(*, k, 1, 5, 1, k)"), 120.0);
+		assert_approx_eq!(run_single_expression("reduce(+, x, 0, 1, 0.1, 1)"), 11.0);
+		assert_approx_eq!(run_single_expression("reduce(+, i, 1, 0, 1, i)"), 0.0);
+		assert_approx_eq!(run_single_expression("reduce(+, i, 1, 3, 1, reduce(*, j, 1, i, 1, j))"), 9.0);
+		expect_expression_to_fail("reduce(max, i, 1, 0, 1, i)");
+		expect_expression_to_fail("reduce(+, i, 1, 5, 0, i)");
+		expect_expression_to_fail("reduce(+, i, 1, 5, 1e-9, i)");
+		expect_expression_to_fail("reduce(+, i, 1, 5, 1, 1/(i - 3))");
+	}
+	
+	#[test]
+	fn test_root() {
+		assert_approx_eq!(run_single_expression("root(27, 3)"), 3.0);
+		assert_approx_eq!(run_single_expression("root(-27, 3)"), -3.0);
+		assert_approx_eq!(run_single_expression("root(16, 0.5)"), 256.0);
+		assert_approx_eq!(run_single_expression("root(16, 4)"), 2.0);
+		assert_approx_eq!(run_single_expression("root(-1, 101)"), -1.0);
+		assert_approx_eq!(run_single_expression("root(8, -3)"), 0.5);
+		expect_expression_to_fail("root(-16, 2)");
+		expect_expression_to_fail("root(16, 0)");
+		expect_expression_to_fail("root(16)");
+	}
+	
+	#[test]
+	fn test_round_modes() {
+		let expected = [
+			(RoundMode::HalfAway, [3.0, -3.0, 4.0, 2.0, -2.0]),
+			(RoundMode::HalfEven, [2.0, -2.0, 4.0, 2.0, -2.0]),
+			(RoundMode::HalfUp, [3.0, -2.0, 4.0, 2.0, -2.0]),
+			(RoundMode::HalfDown, [2.0, -3.0, 3.0, 2.0, -2.0]),
+			(RoundMode::Ceil, [3.0, -2.0, 4.0, 3.0, -2.0]),
+			(RoundMode::Floor, [2.0, -3.0, 3.0, 2.0, -3.0]),
+			(RoundMode::Trunc, [2.0, -2.0, 3.0, 2.0, -2.0])
+		];
+		
+		for (mode, results) in expected {
+			let rounded: Vec<f64> = [2.5, -2.5, 3.5, 2.1, -2.1].iter().map(|x| compute_round(*x, mode)).collect();
+			assert_eq!(rounded, results, "{}", mode);
+		}
 		
+		assert_eq!(compute_round(0.49999999999999994, RoundMode::HalfUp), 0.0);
+		assert_eq!(compute_round(-0.49999999999999994, RoundMode::HalfDown), 0.0);
+	}
+	
+	#[test]
+	fn test_round() {
+		assert_approx_eq!(run_single_expression("round(2.5)"), 3.0);
+		assert_approx_eq!(run_single_expression("round(-2.5)"), -3.0);
+		assert_approx_eq!(run_single_expression("round(2.49)"), 2.0);
+		expect_expression_to_fail("round(1, 2)");
+	}
+	
+	#[test]
+	fn test_sign() {
+		assert_eq!(run_single_expression("sign(2.5)"), 1.0);
+		assert_eq!(run_single_expression("sign(-0.1)"), -1.0);
+		assert_eq!(run_single_expression("sign(0)"), 0.0);
+		assert_eq!(run_single_expression("sign(-0)"), 0.0);
+		expect_expression_to_fail("sign(0/0)");
+		expect_expression_to_fail("sign(1, 2)");
+	}
+	
 	#[test]
 	fn test_sin() {
 		assert_approx_eq!(run_single_expression("sin(1.5 * 3.14159265)"), -1.0);
@@ -326,7 +1940,29 @@ mod tests {
 		assert_approx_eq!(run_single_expression("sin(2.0 / 12.0 * 3.14159265)"), 0.5);
 		expect_expression_to_fail("sin(1.0, 1.0)");
 	}
-
+	
+	#[test]
+	fn test_sind() {
+		assert_eq!(run_single_expression("sind(90)"), 1.0);
+		assert_approx_eq!(run_single_expression("sind(30)"), 0.5);
+		assert_approx_eq!(run_single_expression("sind(-270)"), 1.0);
+		expect_expression_to_fail("sind()");
+	}
+	
+	#[test]
+	fn test_sinh() {
+		assert_approx_eq!(run_single_expression("sinh(0)"), 0.0);
+		assert_approx_eq!(run_single_expression("sinh(1)"), (std::f64::consts::E - 1.0 / std::f64::consts::E) / 2.0);
+		expect_expression_to_fail("sinh(1000)");
+	}
+	
+	#[test]
+	fn test_sinturn() {
+		assert_approx_eq!(run_single_expression("sinturn(0.25)"), 1.0);
+		assert_approx_eq!(run_single_expression("sinturn(0.75)"), -1.0);
+		assert_approx_eq!(run_single_expression("sinturn(1)"), 0.0);
+	}
+	
 	#[test]
 	fn test_sqrt() {
 		assert_approx_eq!(run_single_expression("sqrt(25)"), 5.0);
@@ -334,13 +1970,114 @@ mod tests {
 		expect_expression_to_fail("sqrt(-1.0)");
 		expect_expression_to_fail("sqrt(1.0, 1.0)");
 	}
-
+	
+	#[test]
+	fn test_sum() {
+		assert_approx_eq!(run_single_expression("sum(1, 2, 3)"), 6.0);
+		assert_approx_eq!(run_single_expression("sum(5)"), 5.0);
+		assert_approx_eq!(run_single_expression("sum(-1, 0.5)"), -0.5);
+		expect_expression_to_fail("sum()");
+		expect_expression_to_fail("sum(maxfloat, maxfloat)");
+	}
+	
 	#[test]
 	fn test_tan() {
 		assert_approx_eq!(run_single_expression("tan(0)"), 0.0);
 		assert_approx_eq!(run_single_expression("tan(3.1415926536 / 4)"), 1.0);
 	}
-
+	
+	#[test]
+	fn test_tand() {
+		assert_approx_eq!(run_single_expression("tand(45)"), 1.0);
+		assert_approx_eq!(run_single_expression("tand(-45)"), -1.0);
+	}
+	
+	#[test]
+	fn test_tanh() {
+		assert_approx_eq!(run_single_expression("tanh(0)"), 0.0);
+		assert_approx_eq!(run_single_expression("tanh(1000)"), 1.0);
+		expect_expression_to_fail("tanh()");
+	}
+	
+	#[test]
+	fn test_tanturn() {
+		assert_approx_eq!(run_single_expression("tanturn(0)"), 0.0);
+		assert_approx_eq!(run_single_expression("tanturn(0.125)"), 1.0);
+	}
+	
+	#[test]
+	fn test_trunc() {
+		assert_approx_eq!(run_single_expression("trunc(-1.9)"), -1.0);
+		assert_approx_eq!(run_single_expression("trunc(1.9)"), 1.0);
+		expect_expression_to_fail("trunc()");
+	}
+	
+	#[test]
+	fn test_undefined_result_of_infinite_argument() {
+		for line in ["sin(inf)", "cos(inf)", "tan(inf)", "sind(inf)", "costurn(-inf)"] {
+			let Program::Expr(expr) = Parser::new(line).parse().expect("expression doesn't parse!") else { panic!("not an expression!"); };
+			match expr.evaluate(&mut Context::new()) {
+				Err(e) => { assert!(e.description.ends_with(": result is undefined."), "{}: {}", line, e.description); },
+				Ok(result) => { panic!("{} gave {}!", line, result); }
+			}
+		}
+		
+		expect_expression_to_fail("max(sin(inf), 2)");
+		expect_expression_to_fail("min(2, cos(inf))");
+		expect_expression_to_fail("sin(inf) < 1");
+		expect_expression_to_fail("if(sin(inf), 1, 2)");
+		expect_expression_to_fail("percentile(50, sin(inf), 1, 2)");
+		expect_expression_to_fail("sign(sin(inf))");
+		assert_eq!(run_single_expression("arctan(inf)"), std::f64::consts::FRAC_PI_2);
+	}
+	
+	#[test]
+	fn test_multi_char_variables() {
+		let mut context = Context::new();
+		context.variables.insert(String::from("vel"), 3.0);
+		context.variables.insert(String::from("mass"), 2.0);
+		context.variables.insert(String::from("x1"), 10.0);
+		
+		match Parser::new("mass vel^2 / 2 + x1").parse().expect("expression doesn't parse!") {
+			Program::Expr(expr) => { assert_eq!(expr.evaluate(&mut context).expect("expression doesn't evaluate!"), 19.0); },
+			_ => { panic!("not an expression!"); }
+		}
+		
+		assert_eq!(context.unread_variables(), Vec::<String>::new());
+		expect_expression_to_fail("ve");
+	}
+	
+	#[test]
+	fn test_pi_e_tau() {
+		assert_eq!(run_single_expression("pi"), std::f64::consts::PI);
+		assert_eq!(run_single_expression("2pi"), std::f64::consts::TAU);
+		assert_eq!(run_single_expression("cos(pi)"), -1.0);
+		assert_eq!(run_single_expression("tau / 2"), std::f64::consts::PI);
+		assert_eq!(run_single_expression("e"), std::f64::consts::E);
+		assert_approx_eq!(run_single_expression("ln(e^2)"), 2.0);
+		assert_eq!(run_single_expression("2e1"), 20.0);
+	}
+	
+	#[test]
+	fn test_float_limits() {
+		assert!(run_single_expression("1 + eps") > 1.0);
+		assert!(run_single_expression("eps") < 0.001);
+		assert_eq!(run_single_expression("1 + eps/2"), 1.0);
+		assert_eq!(run_single_expression("maxfloat"), f64::MAX);
+		assert_eq!(run_single_expression("minfloat"), f64::MIN_POSITIVE);
+		assert_eq!(run_single_expression("1/inf"), 0.0);
+		assert_eq!(run_single_expression("inf"), f64::INFINITY);
+		expect_expression_to_fail("inf - inf");
+		expect_expression_to_fail("2maxfloat");
+	}
+	
+	#[test]
+	fn test_phi() {
+		assert_approx_eq!(run_single_expression("phi"), 1.6180339887);
+		assert_approx_eq!(run_single_expression("phi^2 - phi"), 1.0);
+		assert_approx_eq!(run_single_expression("2phi - 1"), 5.0_f64.sqrt());
+	}
+	
 	#[test]
 	fn test_evaluation_order() {
 		assert_approx_eq!(run_single_expression("(-8 - -7) - (-4 / -2)"), (-8.0 - -7.0) - (-4.0 / -2.0));
@@ -349,29 +2086,28 @@ mod tests {
 	}
 	
 	fn run_single_expression(line: &str) -> f64 {
-		let mut parser = Parser::new(&line);
+		let mut parser = Parser::new(line);
 		let program = parser.parse().expect("expression doesn't parse!");
 		
 		match program {
 			Program::Expr(expr) => {
-				let variables = HashMap::<char, f64>::new(); // Not actually used
-				expr.evaluate(&variables).expect("expression doesn't evaluate!")
+				let mut context = Context::new(); // Not actually used
+				expr.evaluate(&mut context).expect("expression doesn't evaluate!")
 			}
 			_ => { panic!("not an expression!"); }
 		}
 	}
 	
 	fn expect_expression_to_fail(line: &str) {
-		let mut parser = Parser::new(&line);
+		let mut parser = Parser::new(line);
 		let program = parser.parse().expect("expression doesn't parse!");
 		
 		match program {
 			Program::Expr(expr) => {
-				let variables = HashMap::<char, f64>::new(); // Not actually used
+				let mut context = Context::new(); // Not actually used
 				
-				match expr.evaluate(&variables) {
-					Ok(_) => { panic!("expression should not evaluate!"); },
-					_ => {}
+				if expr.evaluate(&mut context).is_ok() {
+					panic!("expression should not evaluate!");
 				}
 			}
 			_ => { panic!("not an expression!"); }
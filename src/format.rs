@@ -0,0 +1,172 @@
+// Settings that control how numbers are displayed. Values are always
+// stored at full precision; these only affect what is printed.
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Precision {
+	// The shortest decimal that parses back to exactly the same value.
+	Auto,
+	// A fixed number of decimals.
+	Fixed(usize),
+	// Like Auto, but in scientific notation, e.g. -1.23e5.
+	Scientific
+}
+
+// The base that integer results are displayed in.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Base {
+	Decimal,
+	Hexadecimal,
+	Binary
+}
+
+pub struct Format {
+	pub precision: Precision,
+	pub base: Base,
+	// Append ".0" to integer-valued results that would otherwise be shown
+	// without a decimal point, e.g. 4.0 instead of 4.
+	pub always_decimal_point: bool
+}
+
+impl Format {
+	pub fn new() -> Self {
+		Self {
+			precision: Precision::Auto,
+			base: Base::Decimal,
+			always_decimal_point: false
+		}
+	}
+	
+	pub fn format(&self, value: f64) -> String {
+		if let Some(text) = self.in_base(value) {
+			return text;
+		}
+		
+		let mut text = match self.precision {
+			Precision::Auto => format!("{}", value),
+			Precision::Fixed(decimals) => format!("{:.*}", decimals, value),
+			Precision::Scientific => format!("{:e}", value)
+		};
+		
+		if self.always_decimal_point && value.fract() == 0.0 && !text.contains(['.', 'e']) {
+			text.push_str(".0");
+		}
+		
+		text
+	}
+	
+	// The value with a prefix in the output base, such as 0xff, or None if
+	// the base is decimal or the value is not an integer that fits in 64
+	// bits. A negative value has a sign rather than being shown as two's
+	// complement, so that it reads back the same.
+	pub fn in_base(&self, value: f64) -> Option<String> {
+		if value.fract() != 0.0 || value.abs() >= 2.0_f64.powi(63) {
+			return None;
+		}
+		
+		let sign = if value < 0.0 { "-" } else { "" };
+		let magnitude = value.abs() as u64;
+		
+		match self.base {
+			Base::Decimal => None,
+			Base::Hexadecimal => Some(format!("{sign}{magnitude:#x}")),
+			Base::Binary => Some(format!("{sign}{magnitude:#b}"))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::evaluation::*;
+	use crate::parser::*;
+	
+	#[test]
+	fn auto_precision_shows_all_digits() {
+		let format = Format::new();
+		assert_eq!(format.format(2.0), "2");
+		assert_eq!(format.format(1.0 / 3.0), "0.3333333333333333");
+	}
+	
+	#[test]
+	fn auto_precision_round_trips() {
+		let format = Format::new();
+		
+		for value in [0.1, 1.0 / 3.0, 2.0_f64.sqrt(), 1e-7, 6.02214076e23, -123.456, f64::MAX, f64::MIN_POSITIVE] {
+			let text = format.format(value);
+			assert_eq!(text.parse::<f64>().expect("output doesn't parse!").to_bits(), value.to_bits());
+		}
+	}
+	
+	#[test]
+	fn fixed_precision_rounds_to_decimals() {
+		let mut format = Format::new();
+		format.precision = Precision::Fixed(3);
+		assert_eq!(format.format(2.0), "2.000");
+		assert_eq!(format.format(2.0 / 3.0), "0.667");
+		format.precision = Precision::Fixed(0);
+		assert_eq!(format.format(2.5001), "3");
+	}
+	
+	#[test]
+	fn decimal_point_can_always_be_shown() {
+		let mut format = Format::new();
+		format.always_decimal_point = true;
+		assert_eq!(format.format(4.0), "4.0");
+		assert_eq!(format.format(-4.0), "-4.0");
+		assert_eq!(format.format(0.5), "0.5");
+		assert_eq!(format.format(f64::INFINITY), "inf");
+		format.precision = Precision::Fixed(2);
+		assert_eq!(format.format(4.0), "4.00");
+		format.precision = Precision::Scientific;
+		assert_eq!(format.format(4.0), "4e0");
+	}
+	
+	#[test]
+	fn scientific_precision_uses_exponents() {
+		let mut format = Format::new();
+		format.precision = Precision::Scientific;
+		assert_eq!(format.format(123000.0), "1.23e5");
+		assert_eq!(format.format(-123000.0), "-1.23e5");
+		assert_eq!(format.format(1.0), "1e0");
+	}
+	
+	#[test]
+	fn scientific_precision_round_trips_through_parser() {
+		let mut format = Format::new();
+		format.precision = Precision::Scientific;
+		
+		for value in [123000.0, -123000.0, 1.0 / 3.0 * 1e10, -6.02214076e23, 1.5, -2.0_f64.sqrt(), 0.0, -0.0, f64::MAX, -f64::MAX,
+				1e-7, -1.5e-300, 1.0 / 3.0 * 1e-10, f64::MIN_POSITIVE, -f64::MIN_POSITIVE] {
+			let text = format.format(value);
+			assert_eq!(evaluate(&text).to_bits(), value.to_bits(), "{} doesn't round-trip", text);
+		}
+	}
+	
+	#[test]
+	fn integers_can_be_shown_in_other_bases() {
+		let mut format = Format::new();
+		format.base = Base::Hexadecimal;
+		assert_eq!(format.format(255.0), "0xff");
+		assert_eq!(format.format(-255.0), "-0xff");
+		assert_eq!(format.format(0.0), "0x0");
+		assert_eq!(format.format(2.5), "2.5");
+		assert_eq!(format.format(1e19), "10000000000000000000");
+		assert_eq!(format.format(f64::INFINITY), "inf");
+		format.base = Base::Binary;
+		assert_eq!(format.format(10.0), "0b1010");
+		format.always_decimal_point = true;
+		assert_eq!(format.format(10.0), "0b1010");
+		
+		for value in [255.0, -255.0, 0.0, 9007199254740993.0, -9223372036854774784.0] {
+			let text = format.format(value);
+			assert_eq!(evaluate(&text), value, "{} doesn't round-trip", text);
+		}
+	}
+	
+	fn evaluate(line: &str) -> f64 {
+		match Parser::new(line).parse().expect("output doesn't parse!") {
+			Program::Expr(expr) => expr.evaluate(&mut Context::new()).expect("output doesn't evaluate!"),
+			_ => { panic!("output is not an expression!"); }
+		}
+	}
+}
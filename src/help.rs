@@ -2,50 +2,266 @@ pub fn help() -> String {
 		env!("CARGO_PKG_NAME").to_string() + " " + env!("CARGO_PKG_VERSION") +
 		r#" is an interactive calculator that can be run in a terminal.
 commands:
+alias <name>=<func>
+                makes <name> another name for function <func>, e.g.
+                'alias r=sqrt' makes r(4) the same as sqrt(4).
+alias           lists the aliases.
+assert <expr>   displays an error if <expr> is 0, e.g. 'assert x < 1'. When
+                input is not typed in, the exit code is then 1.
+autorecalc on   makes assigning a variable also recompute the variables
+                that were assigned from expressions using it, and the
+                ones using those, and so on. Assignments that would make
+                variables depend on each other are rejected.
+autorecalc off  turns this off again. This is the default.
+bin             displays integer results in binary, e.g. 0b1010. Other
+                results are still displayed in decimal.
+bits <expr>     displays the IEEE-754 bit pattern of the value of <expr>.
+clear           removes all variables. Labels are kept.
+convert <expr> <from> <to>
+                converts the value of <expr> from unit <from> to unit <to>,
+                e.g. 'convert 1 mi km'. The units are rad, deg, grad and
+                turn for angles, m, km, cm, mm, in, ft, yd, mi and nmi for
+                lengths, kg, g, lb and oz for masses, k, c and f for
+                temperatures, and s, min, h and d for times.
+count           displays the number of variables. Like numsteps, it can
+                also be used as a value, e.g. 'n = count'.
+dec             displays results in decimal again. This is the default.
+decimalpoint always
+                displays integer results with a decimal point, e.g. 4.0.
+decimalpoint auto
+                displays integer results without one. This is the default.
+degrees         makes sin, cos and tan take angles in degrees, and arcsin,
+                arccos, arctan and atan2 give them in degrees.
+del <var>       removes variable <var>.
+dump            displays the settings, variables and labels as input that
+                recreates them, which save writes to a file.
+errors verbose  displays where in the line a syntax or parse error was
+                found, with a ^ under it.
+errors terse    displays just the error. This is the default.
 help            displays this help text.
+hex             displays integer results in hexadecimal, e.g. 0xff.
+history         lists the input lines so far, numbered. When input is
+                typed in a terminal, they are kept in ~/.clicalc_history
+                for the next session, and the up and down arrow keys go
+                through them. Tab completes the name of a command,
+                function or variable being typed.
+load "<file>"   runs the lines of <file>, like --load.
+multiline on    collects input lines until a blank line and evaluates them
+                as one. 'multiline off' followed by a blank line turns
+                this off again.
+numsteps <n>    makes integ divide its range into <n> subintervals. More
+                are more accurate but slower. The default is 1000.
+numsteps        displays the current number of subintervals.
+peek <expr>     evaluates <expr> and displays the result without
+                making it the latest result.
+prec <op>       displays the precedence and associativity of operator <op>.
+precision <n>   displays results rounded to <n> decimals. Variables
+                keep their full precision.
+precision auto  displays results with as few digits as possible without
+                losing precision. This is the default.
+precision sci   like precision auto, but in scientific notation.
+precision       displays the current precision.
 quit            exits.
+radians         makes them use radians again. This is the default.
+recompute <var> assigns <var> again from the expression it was assigned
+                from, using the current values of the variables in it.
+                Variables that depend on <var> are not recomputed.
+roundmode <m>   sets how round() picks an integer. <m> is halfaway, which
+                rounds halfway cases away from zero and is the default,
+                halfeven, halfup or halfdown, which round them to even,
+                up or down, or ceil, floor or trunc, which always round
+                up, down or towards zero.
+roundmode       displays the current rounding mode.
+save "<file>"   writes what dump displays to <file>, to be restored with
+                load.
+search <text>   lists the input lines so far that contain <text>,
+                numbered from the first.
+seed <n>        makes the choices of choose repeatable: after the same
+                seed, they come out the same.
+sigfigs on      displays results computed from number literals with
+                +, -, * and / rounded to their significant figures, e.g.
+                2.0 * 3.00 as 6.0. A product or quotient has as many
+                figures as the operand with the fewest, and a sum or
+                difference as many decimals as the operand with the
+                fewest. Trailing zeros of integers, like those of 1200,
+                are not significant. Variables and constants are exact.
+sigfigs off     turns this off again. This is the default.
+stat add <expr> adds the value of <expr> to a dataset.
+stat            displays the count, mean, minimum, maximum and sample
+                standard deviation of the dataset.
+stat clear      empties the dataset.
+step <expr>     evaluates <expr> one operation at a time, innermost
+                first, and displays each with the values of its operands.
+store <var>     assigns the latest result to variable <var>.
+vars            lists all variables and their values.
+vars def        lists all variables, their values and the expressions
+                they were assigned from.
+why             explains what kind of operation produced the latest result.
 <var> = <expr>  evaluates <expr> and assigns the result to variable <var>.
 <expr>          evaluates <expr> and displays the result.
+<label>: <expr> evaluates <expr> and stores the result as <label>.
++<expr>%        adds <expr> percent to the latest result, e.g. +10%.
+-<expr>%        subtracts <expr> percent from the latest result.
+!<n>            runs line <n> of the history again.
+# <text>        a comment, which is ignored along with the rest of the line.
+<a>; <b>        runs <a> and then <b>, unless <a> fails. Any number of
+                commands, assignments and expressions can be separated
+                with ';'.
 
-<var> is single letter variable name, i.e., one of a..z.
+command line options:
+-q, --quiet         suppresses the startup banner; also done by setting
+                    CLICALC_QUIET=1 or when input is not a terminal.
+--set <var>=<expr>  assigns a variable before anything else is run.
+--load <file>       runs the lines of <file> before starting. Blank lines
+                    and lines starting with # are skipped.
+--warn-unused       lists variables that were assigned but never used
+                    when exiting.
+<expr>              evaluates <expr>, displays the result and exits
+                    instead of starting an interactive session. The exit
+                    code is 1 if <expr> fails, and 0 otherwise.
+
+<var> is a name of lowercase letters, digits and underscores, starting with
+a letter, e.g. x, vel or x_1. Letters written together form a single name, so
+2ab is 2 times ab; write 2a b for 2 times a times b. Names of constants,
+functions and commands cannot be used as variables.
+<label> is a name of two or more lowercase letters, digits and underscores,
+starting with a letter. A label can only be defined once.
 <expr> is a mathematical expression, consisting of any or the following:
 
 <number>          a number literal in the standard format:
-                      [-]nnn[.nnn][e[-]nnn]
-				      [-].nnn[e[-]nnn]
+                      [-]nnn[.[nnn]][e[+-]nnn]
+				      [-].nnn[e[+-]nnn]
+                  The exponent separator may also be written as 'E'.
+                  Digits may be grouped with single underscores, e.g.
+                  1_000_000.
+0xnnn             a hexadecimal integer literal, e.g. 0xff
+0bnnn             a binary integer literal, e.g. 0b1010
+0onnn             an octal integer literal, e.g. 0o17
 expr + expr       addition
 expr - expr       subtraction
 expr * expr       multiplication
 expr / expr       division
-expr ^ expr       exponentiation
+expr // expr      division rounded down to an integer, e.g. -7 // 2 = -4
+expr % expr       remainder of division, which is never negative
+expr ^ expr       exponentiation. A negative number can be raised to a
+                  fraction with an odd denominator, e.g. (-8)^(1/3) = -2.
+expr!             factorial
+expr < expr       1 if true, 0 otherwise. The other comparisons are <=, >,
+                  >=, == and !=.
 -expr             unary negative expression
 +expr             supported for completeness, but basically useless
 (expr)            parentheses can be used to modify the order of evaluation
 abs(expr)         absolute value
+acosd(expr)       arc cosine in degrees
+acosh(expr)       inverse hyperbolic cosine
 arccos(expr)      arc cosine
 arcsin(expr)      arc sine
 arctan(expr)      arc tangent
+asind(expr)       arc sine in degrees
+asinh(expr)       inverse hyperbolic sine
+atan2(y, x)       angle of the point (x, y) from the positive x axis, in
+                  the range -pi..pi. atan2(0, 0) is 0.
+atand(expr)       arc tangent in degrees
+atanh(expr)       inverse hyperbolic tangent
+binompmf(k, n, p) probability of exactly k successes in n trials with
+                  success probability p
+ceil(expr)        smallest integer greater than or equal to the argument
+ceilto(x, m)      smallest multiple of m greater than or equal to x
+choose(e1, e2, ...)
+                  one of the arguments at random. Use the seed command to
+                  make the choices repeatable.
+clamp(x, lo, hi)  x limited to the range lo..hi
 cos(expr)         cosine
+cosd(expr)        cosine of an angle given in degrees
+cosh(expr)        hyperbolic cosine
+costurn(expr)     cosine of an angle given in turns (1 turn = 2 pi radians)
+deg(expr)         radians converted to degrees
 exp(expr)         e to a power
+expm1(expr)       exp(x) - 1, accurate also when x is near zero
+fact(expr)        factorial of a nonnegative integer, also written as n!
+floor(expr)       largest integer less than or equal to the argument
+floorto(x, m)     largest multiple of m less than or equal to x
+gcd(e1, e2, ...)  greatest common divisor of integer arguments
+getenv("NAME")    the value of environment variable NAME, which must be set
+                  to a number
+if(c, a, b)       a if c is nonzero, b otherwise. Only the one that is
+                  selected is evaluated.
+integ(var, lo, hi, expr)
+                  integral of expr over variable var from lo to hi, computed
+                  with Simpson's rule over as many subintervals as set with
+                  numsteps, e.g. integ(x, 0, 1, x^2) = 0.333...
+invlerp(a, b, v)  inverse of lerp, the t for which lerp(a, b, t) = v
+isprime(expr)     1 if the integer argument is a prime, 0 otherwise
+kthlargest(k, e1, ...)
+                  k'th largest of e1, ..., counting from 1
+kthsmallest(k, e1, ...)
+                  k'th smallest of e1, ..., counting from 1
+lcm(e1, e2, ...)  least common multiple of integer arguments
+lerp(a, b, t)     linear interpolation from a to b, a + (b - a) * t
 ln(expr)          natural logarithm (base e)
+ln1p(expr)        ln(1 + x), accurate also when x is near zero
 log(expr)         logarithm (base 10)
+log(expr, b)      logarithm (base b)
+log2(expr)        logarithm (base 2)
 max(e1, e2, ...)  maximum of arguments
+mean(e1, e2, ...) arithmetic mean of arguments, also avg(e1, e2, ...)
 min(e1, e2, ...)  minimum of arguments
+mod(a, b)         remainder of a / b, which is never negative. Same as a % b.
+ncr(n, k)         number of ways to choose k items out of n, ignoring order
+nextprime(expr)   smallest prime greater than the integer argument
+npr(n, k)         number of ordered arrangements of k items out of n
+percentile(p, e1, ...)
+                  p'th percentile of e1, ..., interpolated linearly between
+                  the nearest values
+poissonpmf(k, l)  probability of exactly k events at average rate l
 pow(e1, e1)       e1 to power e2
+powmod(b, e, m)   b to power e, modulo m, for nonnegative integers
+product(e1, ...)  product of arguments
+quadroot1(a, b, c)
+                  the root (-b + sqrt(b^2 - 4a c)) / (2a) of a x^2 + b x + c = 0
+quadroot2(a, b, c)
+                  the root (-b - sqrt(b^2 - 4a c)) / (2a) of a x^2 + b x + c = 0
+rad(expr)         degrees converted to radians
+reduce(op, var, lo, hi, step, expr)
+                  expr evaluated with variable var set to lo, lo + step, and
+                  so on up to hi, and the values combined with op, which is
+                  +, *, max or min, e.g. reduce(+, i, 1, 5, 1, i^2) = 55
+root(x, n)        n'th root of x, i.e. x^(1/n). Negative x has a real root
+                  when n is an odd integer, e.g. root(-27, 3) = -3.
+round(expr)       nearest integer, rounding halfway cases away from zero
+                  unless set otherwise with roundmode
+sign(expr)        -1, 0 or 1 depending on the sign of the argument
 sin(expr)         sine
+sind(expr)        sine of an angle given in degrees
+sinh(expr)        hyperbolic sine
+sinturn(expr)     sine of an angle given in turns
 sqrt(expr)        square root
+sum(e1, e2, ...)  sum of arguments
 tan(expr)         tangent
+tand(expr)        tangent of an angle given in degrees
+tanh(expr)        hyperbolic tangent
+tanturn(expr)     tangent of an angle given in turns
+trunc(expr)       integer part of the argument, rounding towards zero
+e                 Euler's number, 2.71828...
+eps               the difference between 1 and the next larger number
+inf               infinity. It can be used in expressions like 1/inf, but
+                  a result cannot be infinite.
+maxfloat          the largest finite number
+minfloat          the smallest positive normal number
+phi               the golden ratio, (1 + sqrt(5)) / 2
+pi                the ratio of a circle's circumference to its diameter
+tau               2pi
 <var>             previously assigned value of a variable
+ans               the latest result
+<label>           previously computed result stored as a label
 
 Parentheses following a function name are mandatory as evaluation rules
 would otherwise become confusing.
 
 The multiplication sign '*' can be omitted when the right hand operand
-is not a number. The parser will consider anything that looks like a
-function to be such, even when context implies otherwise. That is,
-multiplying 'l' and 'n' can be written as 'l*n', 'l n', 'nl' etc,
-but not "ln" as that always refers to the function, even when not
-followed by an argument list.
+is not a number. Letters written together form a single name, so
+multiplying 'l' and 'n' can be written as 'l*n' or 'l n', but not as
+'ln', which is the function, or 'nl', which is another variable.
 
 Variables can only be referred to after they have been assigned to at
 least once. Variables can be assigned to multiple times, and can be
@@ -55,9 +271,9 @@ x = 10
 x = x + 10
 
 Standard evaluation order applies. Functions, parenthesized subexpressions
-and unary expressions are evaluated first, then exponentiation, then
-multiplication and division, and finally addition and subtraction.
-The expression
+and factorials are evaluated first, then exponentiation, then unary minus,
+then multiplication, both kinds of division and remainder, then addition
+and subtraction, and finally comparisons, so -2^2 is -4. The expression
 6 / 2(1 + 2)
 yields 9 (as it is the correct answer).
 
@@ -67,6 +283,6 @@ Example input:
 a = 2
 b = -5
 c = 3
-(-b + sqrt(b^2 - 4ac)) / (2a)
-(-b - sqrt(b^2 - 4ac)) / (2a)"#
+(-b + sqrt(b^2 - 4a c)) / (2a)
+(-b - sqrt(b^2 - 4a c)) / (2a)"#
 }
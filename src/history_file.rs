@@ -0,0 +1,54 @@
+// Input history kept across interactive sessions in ~/.clicalc_history, one
+// line per input line, oldest first.
+
+use crate::errors::Error;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// The most lines kept in the file; older ones are dropped when saving.
+const MAX_LINES: usize = 1000;
+
+// The history file in the home directory, or None if HOME is not set.
+pub fn history_path() -> Option<PathBuf> {
+	env::var_os("HOME").filter(|home| !home.is_empty()).map(|home| PathBuf::from(home).join(".clicalc_history"))
+}
+
+// Read the history, which is empty if there is no file yet.
+pub fn read_history(path: &PathBuf) -> Result<Vec<String>, Error> {
+	match fs::read_to_string(path) {
+		Ok(text) => Ok(text.lines().filter(|line| !line.trim().is_empty()).map(String::from).collect()),
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+		Err(e) => Err(Error::new(&format!("cannot read {}: {}", path.display(), e)))
+	}
+}
+
+pub fn write_history(path: &PathBuf, lines: &[String]) -> Result<(), Error> {
+	let kept = &lines[lines.len().saturating_sub(MAX_LINES)..];
+	let text: String = kept.iter().map(|line| format!("{line}\n")).collect();
+	fs::write(path, text).map_err(|e| Error::new(&format!("cannot write {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn history_is_written_and_read_back() {
+		let path = env::temp_dir().join(format!("clicalc-history-test-{}.txt", std::process::id()));
+		let lines: Vec<String> = (1..=MAX_LINES + 5).map(|i| format!("x = {i}")).collect();
+		write_history(&path, &lines).expect("cannot write history!");
+		let read = read_history(&path);
+		fs::remove_file(&path).ok();
+		let read = read.expect("cannot read history!");
+		assert_eq!(read.len(), MAX_LINES);
+		assert_eq!(read[0], "x = 6");
+		assert_eq!(read[MAX_LINES - 1], format!("x = {}", MAX_LINES + 5));
+	}
+	
+	#[test]
+	fn missing_history_is_empty() {
+		let path = PathBuf::from("/nonexistent/clicalc-history");
+		assert_eq!(read_history(&path).expect("missing history is an error!"), Vec::<String>::new());
+	}
+}
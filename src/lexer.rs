@@ -1,5 +1,6 @@
 use crate::errors::Error;
 use crate::lexer::utility::*;
+use crate::spellings::*;
 use std::collections::HashMap;
 use std::fmt;
 
@@ -11,84 +12,281 @@ pub enum OperatorType {
 	Minus,
 	Times,
 	DividedBy,
+	FloorDiv,
+	Modulo,
 	Power,
+	Factorial,
 	LeftParen,
 	RightParen,
 	Comma,
-	Assignment
+	Assignment,
+	Colon,
+	Less,
+	LessOrEqual,
+	Greater,
+	GreaterOrEqual,
+	Equal,
+	NotEqual
 }
 
 impl fmt::Display for OperatorType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		let spellings = HashMap::from([
-			(OperatorType::Plus, '+'),
-			(OperatorType::Minus, '-'),
-			(OperatorType::Times, '*'),
-			(OperatorType::DividedBy, '/'),
-			(OperatorType::Power, '^'),
-			(OperatorType::LeftParen, '('),
-			(OperatorType::RightParen, ')'),
-			(OperatorType::Comma, ','),
-			(OperatorType::Assignment, '=')]);
+			(OperatorType::Plus, "+"),
+			(OperatorType::Minus, "-"),
+			(OperatorType::Times, "*"),
+			(OperatorType::DividedBy, "/"),
+			(OperatorType::FloorDiv, "//"),
+			(OperatorType::Modulo, "%"),
+			(OperatorType::Power, "^"),
+			(OperatorType::Factorial, "!"),
+			(OperatorType::LeftParen, "("),
+			(OperatorType::RightParen, ")"),
+			(OperatorType::Comma, ","),
+			(OperatorType::Assignment, "="),
+			(OperatorType::Colon, ":"),
+			(OperatorType::Less, "<"),
+			(OperatorType::LessOrEqual, "<="),
+			(OperatorType::Greater, ">"),
+			(OperatorType::GreaterOrEqual, ">="),
+			(OperatorType::Equal, "=="),
+			(OperatorType::NotEqual, "!=")]);
         write!(f, "{}", spellings.get(self).unwrap())
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Eq, Hash, PartialEq)]
 pub enum FunctionType {
 	Abs,
+	AcosDeg,
+	Acosh,
 	ArcCos,
 	ArcSin,
 	ArcTan,
+	AsinDeg,
+	Asinh,
+	Atan2,
+	AtanDeg,
+	Atanh,
+	BinomPmf,
+	Ceil,
+	CeilTo,
+	Choose,
+	Clamp,
 	Cos,
+	CosDeg,
+	CosTurn,
+	Cosh,
+	Deg,
 	Exp,
+	ExpM1,
+	Fact,
+	Floor,
+	FloorTo,
+	Gcd,
+	GetEnv,
+	If,
+	Integ,
+	InvLerp,
+	IsPrime,
+	KthLargest,
+	KthSmallest,
+	Lcm,
+	Lerp,
 	Ln,
+	Ln1P,
 	Log,
+	Log2,
 	Max,
+	Mean,
 	Min,
+	Mod,
+	Ncr,
+	NextPrime,
+	Npr,
+	Percentile,
+	PoissonPmf,
 	Pow,
+	PowMod,
+	Product,
+	QuadRoot1,
+	QuadRoot2,
+	Rad,
+	Reduce,
+	Root,
+	Round,
+	Sign,
 	Sin,
+	SinDeg,
+	SinTurn,
+	Sinh,
 	Sqrt,
-	Tan
+	Sum,
+	Tan,
+	TanDeg,
+	TanTurn,
+	Tanh,
+	Trunc
+}
+
+impl fmt::Display for FunctionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (spelling, _) = FUNCTION_SPELLINGS.iter()
+			.find(|(_, func)| func == self)
+			.expect("FunctionType::fmt(): function has no spelling.");
+        write!(f, "{}", spelling)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ConstantType {
+	E,
+	Eps,
+	Inf,
+	MaxFloat,
+	MinFloat,
+	Phi,
+	Pi,
+	Tau
+}
+
+impl fmt::Display for ConstantType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (spelling, _) = CONSTANT_SPELLINGS.iter()
+			.find(|(_, constant)| constant == self)
+			.expect("ConstantType::fmt(): constant has no spelling.");
+        write!(f, "{}", spelling)
+    }
 }
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum CommandType {
+	Alias,
+	Assert,
+	AutoRecalc,
+	Bin,
+	Bits,
+	Clear,
+	Convert,
+	Count,
+	Dec,
+	DecimalPoint,
+	Degrees,
+	Del,
+	Dump,
+	Errors,
 	Help,
-	Quit
+	Hex,
+	History,
+	Load,
+	Multiline,
+	NumSteps,
+	Peek,
+	Prec,
+	Precision,
+	Quit,
+	Radians,
+	Recompute,
+	RoundMode,
+	Save,
+	Search,
+	Seed,
+	SigFigs,
+	Stat,
+	Step,
+	Store,
+	Vars,
+	Why
 }
 
-#[derive(Copy, Clone, PartialEq)]
+impl fmt::Display for CommandType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (spelling, _) = COMMAND_SPELLINGS.iter()
+			.find(|(_, cmd)| cmd == self)
+			.expect("CommandType::fmt(): command has no spelling.");
+		write!(f, "{}", spelling)
+	}
+}
+
+#[derive(Clone, PartialEq)]
 pub enum Token {
 	Command(CommandType),
 	Literal(f64),
 	Operator(OperatorType),
-	Variable(char),
+	Variable(String),
+	Constant(ConstantType),
+	Label(String),
 	Function(FunctionType),
+	// A string in double quotes, without the quotes.
+	Text(String),
 	Eol
 }
 
 pub struct Lexer<'a> {
+	// The whole input, of which text is the unlexed remainder.
+	input: &'a str,
 	text: &'a str,
-	current_token: Token
+	// The column where the current token starts.
+	token_column: usize,
+	labels: Vec<String>,
+	aliases: Vec<(String, FunctionType)>,
+	current_token: Token,
+	// The text of the latest number literal, as written.
+	literal_text: &'a str
 }
 
 impl<'a> Lexer<'a> {
 	pub fn new(s: &'a str) -> Self {
+		Self::with_labels(s, Vec::new())
+	}
+	
+	// Labels are names of previously computed results, which are
+	// recognized as such instead of being split into variables.
+	pub fn with_labels(s: &'a str, labels: Vec<String>) -> Self {
+		Self::with_names(s, labels, Vec::new())
+	}
+	
+	// Aliases are additional names for functions, given by the user.
+	pub fn with_names(s: &'a str, labels: Vec<String>, aliases: Vec<(String, FunctionType)>) -> Self {
 		Self {
-			text: &s,
-			current_token: Token::Eol
+			input: s,
+			text: s,
+			token_column: 0,
+			labels,
+			aliases,
+			current_token: Token::Eol,
+			literal_text: ""
 		}
 	}
 
 	pub fn current(&self) -> LexerResult {
-		Ok(self.current_token)
+		Ok(self.current_token.clone())
 	}
 
 	pub fn get_next(&mut self) -> LexerResult {
-		let leading_operator_symbols = "+-*/^(),="; // TODO make this an array
+		let result = self.scan_next();
+		result.map_err(|e| e.at_column(self.column()))
+	}
+	
+	// Return the column where the current token starts, or where the one
+	// that failed to lex does, counting characters from 0.
+	pub fn column(&self) -> usize {
+		self.token_column
+	}
+	
+	fn scan_next(&mut self) -> LexerResult {
+		let leading_operator_symbols = "+-*/%^!(),=:<>"; // TODO make this an array
 		self.skip_whitespace();
 		
+		// A comment runs to the end of the line. Input of several lines is
+		// lexed as one, so the lines following the comment still count.
+		while self.text.starts_with('#') {
+			self.text = &self.text[self.text.find('\n').unwrap_or(self.text.len())..];
+			self.skip_whitespace();
+		}
+		
+		self.token_column = self.input[..self.input.len() - self.text.len()].chars().count();
+		
 		if self.text.is_empty() {
 			self.current_token = Token::Eol;
 			return Ok(Token::Eol);
@@ -102,11 +300,36 @@ impl<'a> Lexer<'a> {
 			self.current_token = self.get_operator()?;
 		} else if first.is_ascii_lowercase() {
 			self.current_token = self.get_name()?;
+		} else if first == '"' {
+			self.current_token = self.get_text()?;
+		} else if first == '∠' {
+			// Phasor notation, as in 5∠30 for magnitude 5 at 30 degrees,
+			// would need complex numbers.
+			return error("phasor notation like 5∠30 is not supported, as there are no complex numbers");
 		} else {
 			return error(&format!("unrecognized character: {}", first));
 		}
 		
-		Ok(self.current_token)
+		Ok(self.current_token.clone())
+	}
+	
+	// Return the unlexed remainder of the input up to a comment, if any, and
+	// move to the end of it. This allows commands to take free-form arguments
+	// that are not tokens.
+	pub fn take_remainder(&mut self) -> &'a str {
+		let remainder = match self.text.find('#') {
+			Some(comment) => &self.text[..comment],
+			None => self.text
+		};
+		self.text = &self.text[self.text.len()..];
+		self.current_token = Token::Eol;
+		remainder
+	}
+	
+	// Return the text of the latest number literal, e.g., to tell "2.0"
+	// from "2".
+	pub fn literal_text(&self) -> &'a str {
+		self.literal_text
 	}
 	
 	// Return the next token without moving to it.
@@ -114,60 +337,134 @@ impl<'a> Lexer<'a> {
 		// Hacky but obvious: save current state, invoke get_next()
 		// and restore state before returning.
 		let saved_text = self.text;
-		let saved_current_token = self.current_token;
+		let saved_current_token = self.current_token.clone();
+		let saved_token_column = self.token_column;
 		let next = self.get_next();
 		self.text = saved_text;
 		self.current_token = saved_current_token;
+		self.token_column = saved_token_column;
 		next
 	}
 	
+	// A string runs to the next double quote, and cannot contain one.
+	fn get_text(&mut self) -> LexerResult {
+		match self.text[1..].find('"') {
+			Some(end) => {
+				let text = &self.text[1..end + 1];
+				self.text = &self.text[end + 2..];
+				Ok(Token::Text(String::from(text)))
+			},
+			None => error("unterminated string")
+		}
+	}
+	
 	fn get_literal(&mut self) -> LexerResult {
 		use utility::scan_digits;
+		
+		if self.text.starts_with("0x") || self.text.starts_with("0X") {
+			return self.get_radix_literal(16);
+		} else if self.text.starts_with("0b") || self.text.starts_with("0B") {
+			return self.get_radix_literal(2);
+		} else if self.text.starts_with("0o") || self.text.starts_with("0O") {
+			return self.get_radix_literal(8);
+		}
+		
 		let mut consumed = 0;
-		consumed += scan_digits(&self.text);
+		consumed += scan_digits(self.text);
 		
-		if self.text[consumed..].chars().next() == Some('.') {
+		// A decimal point needs digits on at least one side of it, so "5."
+		// and ".5" are both fine, but "." alone is not.
+		if self.text[consumed..].starts_with('.') {
+			let integers = consumed;
 			consumed += 1; // Decimal separator
 			let decimals = scan_digits(&self.text[consumed..]);
 			
-			if decimals == 0 {
+			if decimals == 0 && integers == 0 {
 				return error("No digits following '.'");
 			}
 			
 			consumed += decimals;
 		}
 		
-		// Proceed to exponent segment if there is a separator and digits,
-		// leave untouched otherwise.
-		if self.text.chars().nth(consumed) == Some('e') && self.text.chars().nth(consumed + 1).is_some_and(|d| d.is_ascii_digit()) {
-			consumed += 1; // Exponent separator
-			consumed += scan_digits(&self.text[consumed..]);
-		}			
+		// Proceed to exponent segment if there is a separator, an optional
+		// sign and digits. Without digits, a lowercase 'e' is left untouched,
+		// as it is Euler's number in "2e", but an uppercase 'E' can only be a
+		// dangling exponent separator.
+		if let Some(exponent) = self.text[consumed..].strip_prefix(['e', 'E']) {
+			let sign = if exponent.starts_with(['+', '-']) { 1 } else { 0 };
+			let exponent_digits = scan_digits(&exponent[sign..]);
+			
+			if exponent_digits > 0 {
+				consumed += 1 + sign + exponent_digits; // Separator, sign and digits
+			} else if self.text[consumed..].starts_with('E') {
+				return error("No digits following exponent separator 'E'");
+			}
+		}
+		
+		if self.text[consumed..].starts_with('_') {
+			return error("Misplaced '_' in number literal");
+		}
 		
+		self.literal_text = &self.text[..consumed];
 		let val = &self.text[..consumed]
+				.replace('_', "")
 				.parse::<f64>()
 				.expect("Lexer::get_literal(): number literal delimited incorrectly.");
 		self.text = &self.text[consumed..];
 		Ok(Token::Literal(*val))
 	}
 	
+	// Integer literal with a two-character prefix like "0x", in the given radix.
+	fn get_radix_literal(&mut self, radix: u32) -> LexerResult {
+		let prefix = &self.text[..2];
+		let digits = scan_radix_digits(&self.text[2..], radix);
+		
+		if let Some(c) = self.text[2 + digits..].chars().next() && c.is_ascii_digit() {
+			return error(&format!("Invalid digit '{}' in {} literal", c, prefix));
+		} else if self.text[2 + digits..].starts_with('_') {
+			return error("Misplaced '_' in number literal");
+		} else if digits == 0 {
+			return error(&format!("No digits following '{}'", prefix));
+		}
+		
+		let val = match i64::from_str_radix(&self.text[2..2 + digits].replace('_', ""), radix) {
+			Ok(v) => v,
+			Err(_) => { return error(&format!("{} literal is too large", prefix)); }
+		};
+		
+		self.literal_text = &self.text[..2 + digits];
+		self.text = &self.text[2 + digits..];
+		Ok(Token::Literal(val as f64))
+	}
+	
 	fn get_operator(&mut self) -> LexerResult {
-		if let Some(symbol) = self.text.chars().next() {
+		if !self.text.is_empty() {
+			// Longer spellings go first, so that "//" isn't read as two '/'.
 			let operators = [
-				('+', OperatorType::Plus),
-				('-', OperatorType::Minus),
-				('*', OperatorType::Times),
-				('/', OperatorType::DividedBy),
-				('^', OperatorType::Power),
-				('(', OperatorType::LeftParen),
-				(')', OperatorType::RightParen),
-				(',', OperatorType::Comma),
-				('=', OperatorType::Assignment)
+				("//", OperatorType::FloorDiv),
+				("<=", OperatorType::LessOrEqual),
+				(">=", OperatorType::GreaterOrEqual),
+				("==", OperatorType::Equal),
+				("!=", OperatorType::NotEqual),
+				("<", OperatorType::Less),
+				(">", OperatorType::Greater),
+				("+", OperatorType::Plus),
+				("-", OperatorType::Minus),
+				("*", OperatorType::Times),
+				("/", OperatorType::DividedBy),
+				("%", OperatorType::Modulo),
+				("^", OperatorType::Power),
+				("!", OperatorType::Factorial),
+				("(", OperatorType::LeftParen),
+				(")", OperatorType::RightParen),
+				(",", OperatorType::Comma),
+				("=", OperatorType::Assignment),
+				(":", OperatorType::Colon)
 			];
 		
 			for (spelling, operator) in operators.iter() {
-				if symbol == *spelling {
-					self.text = &self.text[1..];
+				if self.text.starts_with(spelling) {
+					self.text = &self.text[spelling.len()..];
 					return Ok(Token::Operator(*operator));
 				}
 			}
@@ -180,54 +477,34 @@ impl<'a> Lexer<'a> {
 		}
 	}
 	
-	// Can return either a Variable, a Function or a Command,
-	// depending on what can be matched.
+	// Can return either a Variable, a Constant, a Label, a Function or a
+	// Command. A name is read greedily as a whole word of lowercase letters,
+	// digits and underscores, so "2ab" is 2 times the variable "ab" rather
+	// than 2*a*b, and "helper" or "sinx" are variables instead of a command
+	// or a function followed by more letters. A word that isn't a command,
+	// a constant or a function spelling is a label if it has two or more
+	// characters and is followed by ':' or has been defined as a label
+	// earlier, and a variable otherwise.
 	fn get_name(&mut self) -> LexerResult {
-		let cmd_spellings = [
-			("help", CommandType::Help),
-			("quit", CommandType::Quit)
-		];
-
-		let func_spellings = [
-			("abs", FunctionType::Abs),
-			("arccos", FunctionType::ArcCos),
-			("arcsin", FunctionType::ArcSin),
-			("arctan", FunctionType::ArcTan),
-			("cos", FunctionType::Cos),
-			("exp", FunctionType::Exp),
-			("ln", FunctionType::Ln),
-			("log", FunctionType::Log),
-			("max", FunctionType::Max),
-			("min", FunctionType::Min),
-			("pow", FunctionType::Pow),
-			("sin", FunctionType::Sin),
-			("sqrt", FunctionType::Sqrt),
-			("tan", FunctionType::Tan)
-		];
-		
-		for item in cmd_spellings.iter() {
-			let (spelling, cmd) = item;
-			
-			if self.text.starts_with(spelling) {
-				self.text = &self.text[spelling.len()..];
-				return Ok(Token::Command(*cmd));
-			}
-		}
-		
-		for item in func_spellings.iter() {
-			let (spelling, func) = item;
+		let word = &self.text[..scan_name(self.text)];
+		let rest = &self.text[word.len()..];
 			
-			if self.text.starts_with(spelling) {
-				let len = spelling.len();
-				self.text = &self.text[len..];
-				return Ok(Token::Function(*func));
-			}
-		}
+		let token = if let Some((_, cmd)) = COMMAND_SPELLINGS.iter().find(|(spelling, _)| *spelling == word) {
+			Token::Command(*cmd)
+		} else if let Some((_, constant)) = CONSTANT_SPELLINGS.iter().find(|(spelling, _)| *spelling == word) {
+			Token::Constant(*constant)
+		} else if let Some((_, func)) = FUNCTION_SPELLINGS.iter().find(|(spelling, _)| *spelling == word) {
+			Token::Function(*func)
+		} else if let Some((_, func)) = self.aliases.iter().find(|(alias, _)| alias == word) {
+			Token::Function(*func)
+		} else if word.len() > 1 && (rest.trim_start().starts_with(':') || self.labels.iter().any(|l| l == word)) {
+			Token::Label(String::from(word))
+		} else {
+			Token::Variable(String::from(word))
+		};
 		
-		let v = self.text.chars().next()
-					.expect("Lexer::get_symbol(): lexer is in an invalid state.");
-		self.text = &self.text[1..];
-		Ok(Token::Variable(v))
+		self.text = rest;
+		Ok(token)
 	}
 	
 	fn skip_whitespace(&mut self) {
@@ -241,14 +518,38 @@ mod utility {
 	use crate::errors::Error;
 	
 	// Return the number of digits at or following the current read position.
+	// A single underscore between two digits is allowed as a group separator,
+	// as in 1_000_000, and counted as part of the digits.
 	pub fn scan_digits(segment: &str) -> usize {
-		for (i, c) in segment.chars().enumerate() {
-			if !c.is_ascii_digit() {
-				return i;
+		scan_radix_digits(segment, 10)
+	}
+	
+	// Return the number of digits in the given radix at or following the current
+	// read position, with group separators as in scan_digits.
+	pub fn scan_radix_digits(segment: &str, radix: u32) -> usize {
+		let chars: Vec<char> = segment.chars().collect();
+		let mut i = 0;
+		
+		while i < chars.len() {
+			let separator = chars[i] == '_' && i > 0 && chars.get(i + 1).is_some_and(|c| c.is_digit(radix));
+			
+			if chars[i].is_digit(radix) || separator {
+				i += 1;
+			} else {
+				break;
 			}
 		}
 
-		segment.len()
+		i
+	}
+	
+	// Return the length of the name, i.e., a lowercase letter followed by
+	// letters, digits and underscores, at the current read position.
+	pub fn scan_name(segment: &str) -> usize {
+		segment.chars()
+			.enumerate()
+			.take_while(|(i, c)| c.is_ascii_lowercase() || (*i > 0 && (c.is_ascii_digit() || *c == '_')))
+			.count()
 	}
 	
 	pub fn error(description: &str) -> super::LexerResult {
@@ -307,10 +608,402 @@ mod tests {
 		let input = String::from("13.25e2e24");
 		let mut lexer = Lexer::new(&input);
 		assert_literal_token_with_value(lexer.get_next(), 1325.0);
-		assert_variable_token_with_name(lexer.get_next(), 'e');
-		assert_literal_token_with_value(lexer.get_next(), 24.0);
+		assert_variable_token_with_name(lexer.get_next(), "e24");
+		assert_eol_token(lexer.get_next());
+	}
+
+	#[test]
+	fn negative_exponent_is_tokenized() {
+		let input = String::from("1e-3");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 0.001);
+		assert_eol_token(lexer.get_next());
+	}
+
+	#[test]
+	fn positive_exponent_is_tokenized() {
+		let input = String::from("1e+3");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 1000.0);
+		assert_eol_token(lexer.get_next());
+	}
+
+	#[test]
+	fn decimal_with_leading_zero_and_negative_exponent_is_tokenized() {
+		let input = String::from(".5e-2");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 0.005);
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn trailing_decimal_point_is_tokenized() {
+		let input = String::from("5. 5.e2 1_000.+2");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 5.0);
+		assert_literal_token_with_value(lexer.get_next(), 500.0);
+		assert_literal_token_with_value(lexer.get_next(), 1000.0);
+		assert_operator_token(lexer.get_next(), OperatorType::Plus);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn decimal_point_alone_is_an_error() {
+		for input in [".", ".e2", "._1"] {
+			assert!(Lexer::new(input).get_next().is_err(), "{} was accepted", input);
+		}
+	}
+	
+	#[test]
+	fn literal_text_is_kept() {
+		let mut lexer = Lexer::new("2.50e3 + 0x1_f");
+		lexer.get_next().expect("literal doesn't lex!");
+		assert_eq!(lexer.literal_text(), "2.50e3");
+		lexer.get_next().expect("operator doesn't lex!");
+		lexer.get_next().expect("literal doesn't lex!");
+		assert_eq!(lexer.literal_text(), "0x1_f");
+	}
+
+	#[test]
+	fn sign_not_followed_by_digits_is_not_part_of_exponent() {
+		let input = String::from("2e-x");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_constant_token(lexer.get_next(), ConstantType::E);
+		assert_operator_token(lexer.get_next(), OperatorType::Minus);
+		assert_variable_token_with_name(lexer.get_next(), "x");
+	}
+
+	#[test]
+	fn spaced_minus_after_e_is_subtraction() {
+		let input = String::from("2e - 3");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_constant_token(lexer.get_next(), ConstantType::E);
+		assert_operator_token(lexer.get_next(), OperatorType::Minus);
+		assert_literal_token_with_value(lexer.get_next(), 3.0);
+		assert_eol_token(lexer.get_next());
+	}
+
+	#[test]
+	fn uppercase_exponent_separator_is_tokenized() {
+		let input = String::from("1E3");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 1000.0);
+		assert_eol_token(lexer.get_next());
+	}
+
+	#[test]
+	fn decimal_with_uppercase_exponent_is_tokenized() {
+		let input = String::from("1.5E2 6.02E23 1E-3");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 150.0);
+		assert_literal_token_with_value(lexer.get_next(), 6.02e23);
+		assert_literal_token_with_value(lexer.get_next(), 0.001);
+	}
+
+	#[test]
+	fn exponent_separator_without_digits_is_left_alone() {
+		let input = String::from("1e");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 1.0);
+		assert_constant_token(lexer.get_next(), ConstantType::E);
+	}
+	
+	#[test]
+	fn dangling_uppercase_exponent_separator_is_an_error() {
+		for input in ["1E", "1.5E+", "2E-x"] {
+			match Lexer::new(input).get_next() {
+				Err(e) => { assert_eq!(e.description, "Syntax error: No digits following exponent separator 'E'.", "{}", input); },
+				Ok(_) => { panic!("{} was accepted!", input); }
+			}
+		}
+	}
+	
+	#[test]
+	fn digit_groups_are_separated_by_underscores() {
+		let input = String::from("1_000 1_000.000_5 1e1_0 0b1111_0000");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 1000.0);
+		assert_literal_token_with_value(lexer.get_next(), 1000.0005);
+		assert_literal_token_with_value(lexer.get_next(), 1e10);
+		assert_literal_token_with_value(lexer.get_next(), 240.0);
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn misplaced_underscore_in_literal_is_an_error() {
+		for input in ["_5", "5_", "5__0", "5_.0", "5._0", "0x_f"] {
+			let input = String::from(input);
+			let mut lexer = Lexer::new(&input);
+			assert!(lexer.get_next().is_err(), "{} was accepted", input);
+		}
+	}
+
+	#[test]
+	fn hexadecimal_literal_is_tokenized() {
+		let input = String::from("0xff 0x1a 0X10 0xFf");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 255.0);
+		assert_literal_token_with_value(lexer.get_next(), 26.0);
+		assert_literal_token_with_value(lexer.get_next(), 16.0);
+		assert_literal_token_with_value(lexer.get_next(), 255.0);
+		assert_eol_token(lexer.get_next());
+	}
+
+	#[test]
+	fn hexadecimal_prefix_without_digits_is_an_error() {
+		let input = String::from("0xg");
+		let mut lexer = Lexer::new(&input);
+		assert!(lexer.get_next().is_err());
+		
+		let input = String::from("0x");
+		let mut lexer = Lexer::new(&input);
+		assert!(lexer.get_next().is_err());
+	}
+
+	#[test]
+	fn too_large_hexadecimal_literal_is_an_error() {
+		let input = String::from("0x10000000000000000");
+		let mut lexer = Lexer::new(&input);
+		assert!(lexer.get_next().is_err());
+	}
+
+	#[test]
+	fn binary_literal_is_tokenized() {
+		let input = String::from("0b0 0b1111 0B101 0b1010+0");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 0.0);
+		assert_literal_token_with_value(lexer.get_next(), 15.0);
+		assert_literal_token_with_value(lexer.get_next(), 5.0);
+		assert_literal_token_with_value(lexer.get_next(), 10.0);
+		assert_operator_token(lexer.get_next(), OperatorType::Plus);
+		assert_literal_token_with_value(lexer.get_next(), 0.0);
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn binary_literal_with_invalid_digit_is_an_error() {
+		let input = String::from("0b2");
+		let mut lexer = Lexer::new(&input);
+		assert!(lexer.get_next().is_err());
+		
+		let input = String::from("0b102");
+		let mut lexer = Lexer::new(&input);
+		assert!(lexer.get_next().is_err());
+		
+		let input = String::from("0b");
+		let mut lexer = Lexer::new(&input);
+		assert!(lexer.get_next().is_err());
+	}
+	
+	#[test]
+	fn octal_literal_is_tokenized() {
+		let input = String::from("0o0 0o777 0O17 0.5");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 0.0);
+		assert_literal_token_with_value(lexer.get_next(), 511.0);
+		assert_literal_token_with_value(lexer.get_next(), 15.0);
+		assert_literal_token_with_value(lexer.get_next(), 0.5);
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn octal_literal_with_invalid_digit_is_an_error() {
+		let input = String::from("0o8");
+		let mut lexer = Lexer::new(&input);
+		assert!(lexer.get_next().is_err());
+		
+		let input = String::from("0o");
+		let mut lexer = Lexer::new(&input);
+		assert!(lexer.get_next().is_err());
+	}
+	
+	#[test]
+	fn word_followed_by_colon_is_a_label() {
+		let input = String::from("quadratic : 2");
+		let mut lexer = Lexer::new(&input);
+		assert_label_token_with_name(lexer.get_next(), "quadratic");
+		assert_operator_token(lexer.get_next(), OperatorType::Colon);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+	}
+	
+	#[test]
+	fn floor_division_is_tokenized_as_one_operator() {
+		let input = String::from("7//2/ /");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 7.0);
+		assert_operator_token(lexer.get_next(), OperatorType::FloorDiv);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_operator_token(lexer.get_next(), OperatorType::DividedBy);
+		assert_operator_token(lexer.get_next(), OperatorType::DividedBy);
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn known_label_is_tokenized() {
+		let input = String::from("2root_1+ab");
+		let mut lexer = Lexer::with_labels(&input, vec![String::from("root_1")]);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_label_token_with_name(lexer.get_next(), "root_1");
+		assert_operator_token(lexer.get_next(), OperatorType::Plus);
+		assert_variable_token_with_name(lexer.get_next(), "ab");
+	}
+	
+	#[test]
+	fn constant_is_tokenized_as_whole_word() {
+		let input = String::from("2phi phix");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_constant_token(lexer.get_next(), ConstantType::Phi);
+		assert_variable_token_with_name(lexer.get_next(), "phix");
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn e_is_a_constant_only_as_a_whole_word() {
+		let input = String::from("e ex 2e");
+		let mut lexer = Lexer::new(&input);
+		assert_constant_token(lexer.get_next(), ConstantType::E);
+		assert_variable_token_with_name(lexer.get_next(), "ex");
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_constant_token(lexer.get_next(), ConstantType::E);
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn variable_name_is_read_greedily() {
+		let input = String::from("2ab vel x1 m_2 sinx");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_variable_token_with_name(lexer.get_next(), "ab");
+		assert_variable_token_with_name(lexer.get_next(), "vel");
+		assert_variable_token_with_name(lexer.get_next(), "x1");
+		assert_variable_token_with_name(lexer.get_next(), "m_2");
+		assert_variable_token_with_name(lexer.get_next(), "sinx");
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn comment_is_skipped() {
+		let input = String::from("# note");
+		let mut lexer = Lexer::new(&input);
+		assert_eol_token(lexer.get_next());
+		
+		let input = String::from("2+x# two and x");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_operator_token(lexer.get_next(), OperatorType::Plus);
+		assert_variable_token_with_name(lexer.get_next(), "x");
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn comment_ends_at_newline() {
+		let input = String::from("2 + # two\n# more\n3");
+		let mut lexer = Lexer::new(&input);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_operator_token(lexer.get_next(), OperatorType::Plus);
+		assert_literal_token_with_value(lexer.get_next(), 3.0);
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn function_name_with_digit_is_tokenized_whole() {
+		let input = String::from("log2(8) log 2");
+		let mut lexer = Lexer::new(&input);
+		assert_function_token(lexer.get_next(), FunctionType::Log2);
+		assert_operator_token(lexer.get_next(), OperatorType::LeftParen);
+		assert_literal_token_with_value(lexer.get_next(), 8.0);
+		assert_operator_token(lexer.get_next(), OperatorType::RightParen);
+		assert_function_token(lexer.get_next(), FunctionType::Log);
+		assert_literal_token_with_value(lexer.get_next(), 2.0);
+		assert_eol_token(lexer.get_next());
+	}
+	
+	#[test]
+	fn alias_is_tokenized_as_function() {
+		let mut lexer = Lexer::with_names("r(4) + rx", Vec::new(), vec![(String::from("r"), FunctionType::Sqrt)]);
+		assert_function_token(lexer.get_next(), FunctionType::Sqrt);
+		lexer.get_next().expect("operator doesn't lex!");
+		lexer.get_next().expect("literal doesn't lex!");
+		lexer.get_next().expect("operator doesn't lex!");
+		lexer.get_next().expect("operator doesn't lex!");
+		assert_variable_token_with_name(lexer.get_next(), "rx");
+	}
+	
+	#[test]
+	fn command_is_tokenized_as_whole_word() {
+		let input = String::from("help");
+		let mut lexer = Lexer::new(&input);
+		assert_command_token(lexer.get_next(), CommandType::Help);
+		assert_eol_token(lexer.get_next());
+	}
+
+	#[test]
+	fn command_followed_by_whitespace_is_tokenized() {
+		let input = String::from("quit  ");
+		let mut lexer = Lexer::new(&input);
+		assert_command_token(lexer.get_next(), CommandType::Quit);
+		assert_eol_token(lexer.get_next());
+	}
+
+	#[test]
+	fn command_prefix_of_longer_word_is_not_a_command() {
+		let input = String::from("helper");
+		let mut lexer = Lexer::new(&input);
+
+		assert_variable_token_with_name(lexer.get_next(), "helper");
+
+		assert_eol_token(lexer.get_next());
+	}
+
+	#[test]
+	fn command_followed_by_digit_is_not_a_command() {
+		let input = String::from("quit2");
+		let mut lexer = Lexer::new(&input);
+		assert_variable_token_with_name(lexer.get_next(), "quit2");
 	}
 
+	#[test]
+	fn string_is_tokenized_without_quotes() {
+		let mut lexer = Lexer::new("getenv(\"HOME_Ä\")");
+		assert!(matches!(lexer.get_next(), Ok(Token::Function(FunctionType::GetEnv))));
+		assert_operator_token(lexer.get_next(), OperatorType::LeftParen);
+		assert!(matches!(lexer.get_next(), Ok(Token::Text(t)) if t == "HOME_Ä"));
+		assert_operator_token(lexer.get_next(), OperatorType::RightParen);
+		assert_eol_token(lexer.get_next());
+		
+		let mut lexer = Lexer::new("\"HOME");
+		assert!(lexer.get_next().is_err());
+	}
+	
+	#[test]
+	fn phasor_angle_is_rejected() {
+		let mut lexer = Lexer::new("5∠30");
+		assert_literal_token_with_value(lexer.get_next(), 5.0);
+		match lexer.get_next() {
+			Err(e) => { assert_eq!(e.description, "Syntax error: phasor notation like 5∠30 is not supported, as there are no complex numbers."); },
+			Ok(_) => { panic!(); }
+		}
+	}
+	
+	#[test]
+	fn error_is_located_at_the_token() {
+		let mut lexer = Lexer::new("1 + 2 $ 3");
+		lexer.get_next().expect("Syntax error");
+		assert_eq!(lexer.column(), 0);
+		lexer.get_next().expect("Syntax error");
+		assert_eq!(lexer.column(), 2);
+		lexer.get_next().expect("Syntax error");
+		
+		match lexer.get_next() {
+			Err(e) => { assert_eq!(e.column, Some(6)); },
+			Ok(_) => { panic!(); }
+		}
+	}
+	
 	fn assert_literal_token_with_value(token: LexerResult, value: f64) {
 		match token.expect("Syntax error") {
 			Token::Literal(v) => { assert_eq!(v, value); },
@@ -318,12 +1011,54 @@ mod tests {
 		}
 	}
 	
-	fn assert_variable_token_with_name(token: LexerResult, name: char) {
+	fn assert_variable_token_with_name(token: LexerResult, name: &str) {
 		match token.expect("Syntax error") {
 			Token::Variable(c) => { assert_eq!(c, name); },
 			_ => { panic!(); }
 		}
 	}
 	
+	fn assert_function_token(token: LexerResult, func: FunctionType) {
+		match token.expect("Syntax error") {
+			Token::Function(f) => { assert!(f == func); },
+			_ => { panic!(); }
+		}
+	}
+	
+	fn assert_constant_token(token: LexerResult, constant: ConstantType) {
+		match token.expect("Syntax error") {
+			Token::Constant(c) => { assert!(c == constant); },
+			_ => { panic!(); }
+		}
+	}
+	
+	fn assert_label_token_with_name(token: LexerResult, name: &str) {
+		match token.expect("Syntax error") {
+			Token::Label(l) => { assert_eq!(l, name); },
+			_ => { panic!(); }
+		}
+	}
+	
+	fn assert_command_token(token: LexerResult, command: CommandType) {
+		match token.expect("Syntax error") {
+			Token::Command(c) => { assert!(c == command); },
+			_ => { panic!(); }
+		}
+	}
+	
+	fn assert_operator_token(token: LexerResult, operator: OperatorType) {
+		match token.expect("Syntax error") {
+			Token::Operator(o) => { assert!(o == operator); },
+			_ => { panic!(); }
+		}
+	}
+	
+	fn assert_eol_token(token: LexerResult) {
+		match token.expect("Syntax error") {
+			Token::Eol => {},
+			_ => { panic!(); }
+		}
+	}
+	
 	// TODO: add more tests
 }
@@ -0,0 +1,432 @@
+// Line editing for input typed in a terminal: moving within the line with
+// the arrow keys, going through earlier lines with up and down, and
+// completing names with Tab. The terminal is put in raw mode with stty
+// while a line is being read, so that keys arrive as they are pressed, and
+// restored before it is run.
+
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::process::Command;
+use std::process::Stdio;
+
+pub struct LineEditor<R: Read, W: Write> {
+	input: R,
+	output: W,
+	// The terminal settings to restore after reading a line, or None if
+	// the input is not a terminal, as in tests.
+	terminal_settings: Option<String>
+}
+
+impl LineEditor<io::StdinLock<'static>, io::Stdout> {
+	// A line editor on the terminal, or None if it can't be put in raw
+	// mode, e.g. as there is no stty.
+	pub fn for_terminal() -> Option<Self> {
+		let settings = stty(&["-g"])?;
+		
+		let mut editor = Self::new(io::stdin().lock(), io::stdout());
+		editor.terminal_settings = Some(String::from(settings.trim()));
+		Some(editor)
+	}
+}
+
+impl<R: Read, W: Write> LineEditor<R, W> {
+	pub fn new(input: R, output: W) -> Self {
+		Self {
+			input,
+			output,
+			terminal_settings: None
+		}
+	}
+	
+	// Read a line, which can be edited until Enter is pressed, and return
+	// it without the line break. Up and down go through the history, oldest
+	// first. Tab completes the line up to the cursor with what complete()
+	// returns for it: where the name being completed starts, and the names
+	// it can become. Return None at the end of input, or on Ctrl-D on an
+	// empty line.
+	pub fn read_line<F: Fn(&str) -> (usize, Vec<String>)>(&mut self, history: &[String], complete: F) -> io::Result<Option<String>> {
+		let _raw_mode = match &self.terminal_settings {
+			Some(settings) => Some(RawMode::enable(settings)?),
+			None => None
+		};
+		
+		let mut line = EditedLine::new(history);
+		
+		loop {
+			let Some(key) = self.read_key()? else {
+				return Ok(None);
+			};
+			
+			match key {
+				Key::Enter => {
+					self.output.write_all(b"\r\n")?;
+					self.output.flush()?;
+					return Ok(Some(line.text()));
+				},
+				Key::EndOfInput if line.chars.is_empty() => {
+					self.output.write_all(b"\r\n")?;
+					self.output.flush()?;
+					return Ok(None);
+				},
+				Key::Interrupt => {
+					// Like in a shell, Ctrl-C abandons the line.
+					self.output.write_all(b"^C\r\n")?;
+					line = EditedLine::new(history);
+				},
+				Key::Tab => {
+					let before: String = line.chars[..line.cursor].iter().collect();
+					let (start, names) = complete(&before);
+					
+					if names.is_empty() {
+						self.output.write_all(b"\x07")?;
+					} else if !line.complete(before[..start].chars().count(), &names) {
+						// Nothing to add that all of them share, so list them.
+						write!(self.output, "\r\n{}\r\n", names.join("  "))?;
+					}
+				},
+				_ => {
+					line.apply(key);
+				}
+			}
+			
+			self.redraw(&line)?;
+		}
+	}
+	
+	// Show the line from its start, with the cursor in place.
+	fn redraw(&mut self, line: &EditedLine) -> io::Result<()> {
+		write!(self.output, "\r\x1b[K{}", line.text())?;
+		
+		if line.cursor < line.chars.len() {
+			write!(self.output, "\x1b[{}D", line.chars.len() - line.cursor)?;
+		}
+		
+		self.output.flush()
+	}
+	
+	// Return the next key, or None at the end of input. Escape sequences
+	// that aren't keys known here are ignored.
+	fn read_key(&mut self) -> io::Result<Option<Key>> {
+		loop {
+			let Some(byte) = self.read_byte()? else {
+				return Ok(None);
+			};
+			
+			let key = match byte {
+				b'\r' | b'\n' => Some(Key::Enter),
+				0x01 => Some(Key::Home),
+				0x03 => Some(Key::Interrupt),
+				0x04 => Some(Key::EndOfInput),
+				0x05 => Some(Key::End),
+				0x08 | 0x7f => Some(Key::Backspace),
+				b'\t' => Some(Key::Tab),
+				0x1b => self.read_escape_sequence()?,
+				0x00..=0x1f => None,
+				_ => self.read_char(byte)?.map(Key::Char)
+			};
+			
+			if key.is_some() {
+				return Ok(key);
+			}
+		}
+	}
+	
+	// Keys like the arrows send ESC [ and a letter, or ESC O and a letter,
+	// and some send ESC [, a digit and '~'.
+	fn read_escape_sequence(&mut self) -> io::Result<Option<Key>> {
+		let Some(b'[' | b'O') = self.read_byte()? else {
+			return Ok(None);
+		};
+		
+		Ok(match self.read_byte()? {
+			Some(b'A') => Some(Key::Up),
+			Some(b'B') => Some(Key::Down),
+			Some(b'C') => Some(Key::Right),
+			Some(b'D') => Some(Key::Left),
+			Some(b'H') => Some(Key::Home),
+			Some(b'F') => Some(Key::End),
+			Some(digit @ b'0'..=b'9') => {
+				let key = match digit {
+					b'1' | b'7' => Some(Key::Home),
+					b'3' => Some(Key::Delete),
+					b'4' | b'8' => Some(Key::End),
+					_ => None
+				};
+				
+				// Skip to the end of the sequence.
+				while let Some(b'0'..=b'9' | b';') = self.read_byte()? {}
+				
+				key
+			},
+			_ => None
+		})
+	}
+	
+	// A character may take several bytes in UTF-8.
+	fn read_char(&mut self, first: u8) -> io::Result<Option<char>> {
+		let length = match first {
+			0xc0..=0xdf => 2,
+			0xe0..=0xef => 3,
+			0xf0..=0xf7 => 4,
+			_ => 1
+		};
+		
+		let mut bytes = vec![first];
+		
+		while bytes.len() < length {
+			match self.read_byte()? {
+				Some(byte) => { bytes.push(byte); },
+				None => { break; }
+			}
+		}
+		
+		Ok(std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next()))
+	}
+	
+	fn read_byte(&mut self) -> io::Result<Option<u8>> {
+		let mut byte = [0];
+		
+		loop {
+			match self.input.read(&mut byte) {
+				Ok(0) => { return Ok(None); },
+				Ok(_) => { return Ok(Some(byte[0])); },
+				Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+				Err(e) => { return Err(e); }
+			}
+		}
+	}
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Key {
+	Char(char),
+	Enter,
+	Backspace,
+	Delete,
+	Left,
+	Right,
+	Home,
+	End,
+	Up,
+	Down,
+	Tab,
+	Interrupt,
+	EndOfInput
+}
+
+// The line being edited, and where it is in the history.
+struct EditedLine<'a> {
+	chars: Vec<char>,
+	cursor: usize,
+	history: &'a [String],
+	// The history line shown, or history.len() for the new line.
+	history_index: usize,
+	// The new line as it was before going up in the history.
+	draft: Vec<char>
+}
+
+impl<'a> EditedLine<'a> {
+	fn new(history: &'a [String]) -> Self {
+		Self {
+			chars: Vec::new(),
+			cursor: 0,
+			history,
+			history_index: history.len(),
+			draft: Vec::new()
+		}
+	}
+	
+	fn text(&self) -> String {
+		self.chars.iter().collect()
+	}
+	
+	// Extend the name starting at start and ending at the cursor as far as
+	// all the names share. Return false if it can't be extended.
+	fn complete(&mut self, start: usize, names: &[String]) -> bool {
+		let mut shared: Vec<char> = names[0].chars().collect();
+		
+		for name in &names[1..] {
+			let common = shared.iter().zip(name.chars()).take_while(|(a, b)| **a == *b).count();
+			shared.truncate(common);
+		}
+		
+		let typed = self.cursor - start;
+		
+		if shared.len() <= typed {
+			return false;
+		}
+		
+		for &c in &shared[typed..] {
+			self.chars.insert(self.cursor, c);
+			self.cursor += 1;
+		}
+		
+		true
+	}
+	
+	fn apply(&mut self, key: Key) {
+		match key {
+			Key::Char(c) => {
+				self.chars.insert(self.cursor, c);
+				self.cursor += 1;
+			},
+			Key::Backspace if self.cursor > 0 => {
+				self.cursor -= 1;
+				self.chars.remove(self.cursor);
+			},
+			Key::Delete | Key::EndOfInput if self.cursor < self.chars.len() => {
+				self.chars.remove(self.cursor);
+			},
+			Key::Left if self.cursor > 0 => {
+				self.cursor -= 1;
+			},
+			Key::Right if self.cursor < self.chars.len() => {
+				self.cursor += 1;
+			},
+			Key::Home => {
+				self.cursor = 0;
+			},
+			Key::End => {
+				self.cursor = self.chars.len();
+			},
+			Key::Up if self.history_index > 0 => {
+				if self.history_index == self.history.len() {
+					self.draft = self.chars.clone();
+				}
+				
+				self.history_index -= 1;
+				self.chars = self.history[self.history_index].chars().collect();
+				self.cursor = self.chars.len();
+			},
+			Key::Down if self.history_index < self.history.len() => {
+				self.history_index += 1;
+				self.chars = match self.history.get(self.history_index) {
+					Some(line) => line.chars().collect(),
+					None => self.draft.clone()
+				};
+				self.cursor = self.chars.len();
+			},
+			_ => {}
+		}
+	}
+}
+
+// Raw mode lasts as long as this, so the terminal is restored even when
+// reading fails.
+struct RawMode {
+	settings: String
+}
+
+impl RawMode {
+	fn enable(settings: &str) -> io::Result<Self> {
+		match stty(&["raw", "-echo"]) {
+			Some(_) => Ok(Self { settings: String::from(settings) }),
+			None => Err(io::Error::other("cannot set the terminal to raw mode"))
+		}
+	}
+}
+
+impl Drop for RawMode {
+	fn drop(&mut self) {
+		stty(&[&self.settings]);
+	}
+}
+
+// Run stty on the terminal and return its output, or None if it failed.
+fn stty(args: &[&str]) -> Option<String> {
+	let output = Command::new("stty")
+		.args(args)
+		.stdin(Stdio::inherit())
+		.stderr(Stdio::null())
+		.output()
+		.ok()?;
+	
+	if output.status.success() {
+		String::from_utf8(output.stdout).ok()
+	} else {
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn line_is_read_with_edits() {
+		assert_eq!(lines_of(b"1 + 2\n", &[]), vec!["1 + 2"]);
+		assert_eq!(lines_of(b"1 + 3\x7f2\n", &[]), vec!["1 + 2"]);
+		assert_eq!(lines_of(b"1  2\x1b[D\x1b[D+\n", &[]), vec!["1 + 2"]);
+		assert_eq!(lines_of(b"+ 2\x01\x1b[C\x1b[C\x1b[C\x1b[C\x1bOH1 \n", &[]), vec!["1 + 2"]);
+		assert_eq!(lines_of(b"x1 + 2\x1b[H\x1b[3~\x1b[F\r", &[]), vec!["1 + 2"]);
+		assert_eq!(lines_of("5∠30\x1b[D\x1b[D\x7f\n".as_bytes(), &[]), vec!["530"]);
+	}
+	
+	#[test]
+	fn up_and_down_go_through_history() {
+		let history = [String::from("x = 2"), String::from("y = 3")];
+		assert_eq!(lines_of(b"\x1b[A\n", &history), vec!["y = 3"]);
+		assert_eq!(lines_of(b"\x1b[A\x1b[A\x1b[A\x7f5\n", &history), vec!["x = 5"]);
+		assert_eq!(lines_of(b"z\x1b[A\x1b[A\x1b[B\x1b[B\n", &history), vec!["z"]);
+		assert_eq!(lines_of(b"\x1bOA\x1bOB\n", &history), vec![""]);
+	}
+	
+	#[test]
+	fn end_of_input_ends_reading() {
+		assert_eq!(lines_of(b"1\n2", &[]), vec!["1"]);
+		assert_eq!(lines_of(b"1\n\x04ignored\n", &[]), vec!["1"]);
+		assert_eq!(lines_of(b"12\x01\x04\n", &[]), vec!["2"]);
+		assert_eq!(lines_of(b"1 +\x03 2\n", &[]), vec![" 2"]);
+	}
+	
+	#[test]
+	fn tab_completes_names() {
+		let names = |line: &str| {
+			let start = line.rfind(|c: char| !c.is_ascii_lowercase()).map_or(0, |i| i + 1);
+			let names = ["max", "maxval", "mean", "min", "sqrt"].iter()
+				.filter(|name| name.starts_with(&line[start..]))
+				.map(|name| String::from(*name))
+				.collect();
+			(start, names)
+		};
+		
+		let (line, output) = read_with(b"sq\t(16)\n", names);
+		assert_eq!(line, "sqrt(16)");
+		assert_eq!(output, "\r\x1b[Ks\r\x1b[Ksq\r\x1b[Ksqrt\r\x1b[Ksqrt(\r\x1b[Ksqrt(1\r\x1b[Ksqrt(16\r\x1b[Ksqrt(16)\r\n");
+		assert_eq!(read_with(b"2 * m\t\n", names).1, "\r\x1b[K2\r\x1b[K2 \r\x1b[K2 *\r\x1b[K2 * \r\x1b[K2 * m\r\nmax  maxval  mean  min\r\n\r\x1b[K2 * m\r\n");
+		assert_eq!(read_with(b"ma\t\n", names).0, "max");
+		assert_eq!(read_with(b"(x)\x1b[D\x1b[Dsqr\t\n", names).0, "(sqrtx)");
+		assert_eq!(read_with(b"zz\t\n", names).1, "\r\x1b[Kz\r\x1b[Kzz\x07\r\x1b[Kzz\r\n");
+	}
+	
+	#[test]
+	fn line_is_redrawn_with_cursor() {
+		let mut output = Vec::new();
+		LineEditor::new(&b"ab\x1b[D\n"[..], &mut output).read_line(&[], no_names).expect("input error!");
+		assert_eq!(String::from_utf8(output).expect("output is not UTF-8!"), "\r\x1b[Ka\r\x1b[Kab\r\x1b[Kab\x1b[1D\r\n");
+	}
+	
+	// Read lines until the end of input.
+	fn lines_of(input: &[u8], history: &[String]) -> Vec<String> {
+		let mut editor = LineEditor::new(input, Vec::new());
+		let mut lines = Vec::new();
+		
+		while let Some(line) = editor.read_line(history, no_names).expect("input error!") {
+			lines.push(line);
+		}
+		
+		lines
+	}
+	
+	// Read a line with completion and return it with the output.
+	fn read_with<F: Fn(&str) -> (usize, Vec<String>)>(input: &[u8], complete: F) -> (String, String) {
+		let mut output = Vec::new();
+		let line = LineEditor::new(input, &mut output).read_line(&[], complete).expect("input error!").expect("no line!");
+		(line, String::from_utf8(output).expect("output is not UTF-8!"))
+	}
+
+	fn no_names(_: &str) -> (usize, Vec<String>) {
+		(0, Vec::new())
+	}
+}
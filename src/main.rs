@@ -1,36 +1,109 @@
+mod completion;
 mod help;
 mod lexer;
+mod line_editor;
 mod parser;
 mod errors;
 mod evaluation;
+mod format;
+mod history_file;
+mod options;
+mod random;
 mod runner;
+mod significance;
+mod spellings;
+mod units;
 
 use std::io;
+use std::process;
+use crate::history_file::*;
+use crate::line_editor::LineEditor;
+use crate::options::*;
 use crate::parser::*;
 use crate::runner::*;
 
 fn main() {
-	println!("{}", env!("CARGO_PKG_NAME").to_string() + " " + env!("CARGO_PKG_VERSION"));
-    println!("Type ? or help for usage, quit to exit.");
+	let options = Options::from_env().unwrap_or_else(|e| {
+		eprintln!("{}", e.description);
+		process::exit(2);
+	});
 	
-	let mut keep_going = true;
 	let mut runner = Runner::new();
 	
-	while keep_going {
-		let mut line = String::new();
-		io::stdin()
-			.read_line(&mut line)
-			.expect("Input error!");
-
-		let mut parser = Parser::new(&line);
-		
-		match parser.parse() {
-			Ok(program) => {
-				keep_going = runner.run(&program);
+	for assignment in &options.assignments {
+		if let Err(e) = runner.preset(assignment) {
+			eprintln!("{}", e.description);
+			process::exit(2);
+		}
+	}
+	
+	let mut keep_going = true;
+	let mut exit_code = 0;
+	
+	for script in &options.scripts {
+		match runner.load(script) {
+			Ok(true) => {},
+			Ok(false) => {
+				keep_going = false;
+				break;
 			},
 			Err(e) => {
-				println!("{}", e.description);
+				eprintln!("{}", e.description);
+				process::exit(2);
 			}
 		}
 	}
+	
+	if keep_going {
+		if let Some(expression) = &options.expression {
+			exit_code = runner.run_once(expression);
+		} else {
+			run_interactive(&options, &mut runner);
+		}
+	}
+	
+	// A failed assert in a script or piped input fails the run as a whole.
+	if !options.interactive && runner.failed_assertions() > 0 {
+		exit_code = 1;
+	}
+	
+	if options.warn_unused {
+		for var in runner.unused_variables() {
+			eprintln!("warning: variable {} is assigned but never used", var);
+		}
+	}
+	
+	process::exit(exit_code);
+}
+
+fn run_interactive(options: &Options, runner: &mut Runner<io::Stdout>) {
+	if !options.quiet {
+		println!("{}", env!("CARGO_PKG_NAME").to_string() + " " + env!("CARGO_PKG_VERSION"));
+		println!("Type ? or help for usage, quit to exit.");
+	}
+	
+	// History is kept across sessions only when typed in, not for piped
+	// input.
+	let history = if options.interactive { history_path() } else { None };
+	
+	if let Some(path) = &history {
+		match read_history(path) {
+			Ok(lines) => { runner.set_history(lines); },
+			Err(e) => { eprintln!("{}", e.description); }
+		}
+	}
+	
+	// Typed-in lines can be edited, unless the terminal doesn't allow it.
+	let result = match LineEditor::for_terminal().filter(|_| options.interactive) {
+		Some(mut editor) => runner.run_editor(&mut editor),
+		None => runner.run_stream(io::stdin().lock())
+	};
+	
+	if let Err(e) = result {
+		eprintln!("{}", e.description);
+	}
+	
+	if let Some(path) = &history && let Err(e) = write_history(path, runner.history()) {
+		eprintln!("{}", e.description);
+	}
 }
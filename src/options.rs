@@ -0,0 +1,152 @@
+use crate::errors::Error;
+use std::env;
+use std::io;
+use std::io::IsTerminal;
+
+pub struct Options {
+	// Whether input comes from a terminal rather than, e.g., a pipe.
+	pub interactive: bool,
+	// Suppress the startup banner.
+	pub quiet: bool,
+	// Report variables that were assigned but never used when exiting.
+	pub warn_unused: bool,
+	// Assignments given with --set, to be run before anything else.
+	pub assignments: Vec<String>,
+	// Script files given with --load, to be run after the assignments.
+	pub scripts: Vec<String>,
+	// Non-option arguments joined together, to be evaluated instead of
+	// starting an interactive session.
+	pub expression: Option<String>
+}
+
+impl Options {
+	pub fn from_env() -> Result<Self, Error> {
+		Self::parse(env::args().skip(1), env::var("CLICALC_QUIET").ok(), io::stdin().is_terminal())
+	}
+	
+	// The banner is shown only in interactive use, unless suppressed either
+	// by --quiet/-q or by setting CLICALC_QUIET to anything but "" or "0".
+	pub fn parse<I: Iterator<Item = String>>(args: I, quiet_env: Option<String>, interactive: bool) -> Result<Self, Error> {
+		let mut options = Self {
+			interactive,
+			quiet: !interactive || quiet_env.is_some_and(|v| !v.is_empty() && v != "0"),
+			warn_unused: false,
+			assignments: Vec::new(),
+			scripts: Vec::new(),
+			expression: None
+		};
+		
+		let mut expression_words = Vec::<String>::new();
+		let mut args = args;
+		
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"-q" | "--quiet" => {
+					options.quiet = true;
+				},
+				"--warn-unused" => {
+					options.warn_unused = true;
+				},
+				"--set" => {
+					match args.next() {
+						Some(assignment) => { options.assignments.push(assignment); },
+						None => { return Err(Error::new("--set requires an assignment, e.g. --set a=2")); }
+					}
+				},
+				"--load" => {
+					match args.next() {
+						Some(path) => { options.scripts.push(path); },
+						None => { return Err(Error::new("--load requires a file name")); }
+					}
+				},
+				_ if arg.starts_with('-') && arg.len() > 1 && !arg[1..].starts_with(|c: char| c.is_ascii_digit() || c == '.') => {
+					return Err(Error::new(&format!("unrecognized argument: {}", arg)));
+				},
+				_ => {
+					expression_words.push(arg);
+				}
+			}
+		}
+		
+		if !expression_words.is_empty() {
+			options.expression = Some(expression_words.join(" "));
+		}
+		
+		Ok(options)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn banner_is_shown_by_default_in_interactive_use() {
+		assert!(!parse(&[], None, true).quiet);
+		assert!(!parse(&[], Some("0"), true).quiet);
+		assert!(!parse(&[], Some(""), true).quiet);
+	}
+	
+	#[test]
+	fn banner_is_suppressed_by_flag() {
+		assert!(parse(&["-q"], None, true).quiet);
+		assert!(parse(&["--quiet"], None, true).quiet);
+	}
+	
+	#[test]
+	fn banner_is_suppressed_by_environment() {
+		assert!(parse(&[], Some("1"), true).quiet);
+	}
+	
+	#[test]
+	fn banner_is_suppressed_when_not_interactive() {
+		assert!(parse(&[], None, false).quiet);
+		assert!(!parse(&[], None, false).interactive);
+		assert!(parse(&["-q"], None, true).interactive);
+	}
+	
+	#[test]
+	fn multiple_assignments_are_collected_in_order() {
+		let options = parse(&["--set", "a=2", "--set", "b = 3", "a*b"], None, true);
+		assert_eq!(options.assignments, vec![String::from("a=2"), String::from("b = 3")]);
+		assert_eq!(options.expression, Some(String::from("a*b")));
+	}
+	
+	#[test]
+	fn expression_arguments_are_joined() {
+		assert_eq!(parse(&["2", "+", "2"], None, true).expression, Some(String::from("2 + 2")));
+		assert_eq!(parse(&["-2", "*", "3"], None, true).expression, Some(String::from("-2 * 3")));
+		assert_eq!(parse(&[], None, true).expression, None);
+	}
+	
+	#[test]
+	fn unused_warnings_are_off_by_default() {
+		assert!(!parse(&[], None, false).warn_unused);
+		assert!(parse(&["--warn-unused"], None, false).warn_unused);
+	}
+	
+	#[test]
+	fn set_without_assignment_is_rejected() {
+		assert!(Options::parse(args(&["--set"]), None, true).is_err());
+	}
+	
+	#[test]
+	fn scripts_are_collected_in_order() {
+		let options = parse(&["--load", "a.txt", "--load", "b.txt"], None, true);
+		assert_eq!(options.scripts, vec![String::from("a.txt"), String::from("b.txt")]);
+		assert!(Options::parse(args(&["--load"]), None, true).is_err());
+	}
+	
+	#[test]
+	fn unknown_argument_is_rejected() {
+		assert!(Options::parse(args(&["--loud"]), None, true).is_err());
+	}
+	
+	fn parse(a: &[&str], quiet_env: Option<&str>, interactive: bool) -> Options {
+		Options::parse(args(a), quiet_env.map(String::from), interactive).expect("options don't parse!")
+	}
+	
+	fn args(a: &[&str]) -> impl Iterator<Item = String> {
+		a.iter().map(|s| s.to_string()).collect::<Vec<String>>().into_iter()
+	}
+}
@@ -1,18 +1,30 @@
 use crate::lexer::*;
 use crate::errors::Error;
+use crate::evaluation::ANS;
+use std::collections::BTreeSet;
+use std::fmt;
 use utility::error;
 
 type ParseResult<T> = Result<T, Error>;
 
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone)]
 pub enum Expression {
 	ParenExpr(Box<ParenExpression>),
 	UnaryExpr(Box<UnaryExpression>),
 	BinaryExpr(Box<BinaryExpression>),
 	FunctionExpr(Box<FunctionExpression>),
 	VariableExpr(Box<VariableExpression>),
-	LiteralExpr(Box<LiteralExpression>)
+	ConstantExpr(Box<ConstantExpression>),
+	LabelExpr(Box<LabelExpression>),
+	LiteralExpr(Box<LiteralExpression>),
+	ReduceExpr(Box<ReduceExpression>),
+	IntegralExpr(Box<IntegralExpression>),
+	EnvExpr(Box<EnvExpression>),
+	CommandValueExpr(Box<CommandValueExpression>)
 }
 
+#[derive(Clone)]
 pub struct ParenExpression {
 	pub expr: Expression
 }
@@ -25,6 +37,7 @@ impl ParenExpression {
 	}
 }
 
+#[derive(Clone)]
 pub struct UnaryExpression {
 	pub op: OperatorType,
 	pub expr: Expression
@@ -39,6 +52,7 @@ impl UnaryExpression {
 	}
 }
 
+#[derive(Clone)]
 pub struct BinaryExpression {
 	pub op: OperatorType,
 	pub left: Expression,
@@ -55,6 +69,7 @@ impl BinaryExpression {
 	}
 }
 
+#[derive(Clone)]
 pub struct FunctionExpression {
 	pub func: FunctionType,
 	pub args: Vec<Expression>
@@ -69,43 +84,248 @@ impl FunctionExpression {
     }
 }
 
+#[derive(Clone)]
 pub struct VariableExpression {
-	pub var: char
+	pub var: String
 }
 
 impl VariableExpression {
-	pub fn new(c: char) -> Self {
+	pub fn new(name: String) -> Self {
 		Self {
-			var: c
+			var: name
 		}
 	}
 }
 
+#[derive(Clone)]
+pub struct ConstantExpression {
+	pub constant: ConstantType
+}
+
+impl ConstantExpression {
+	pub fn new(c: ConstantType) -> Self {
+		Self {
+			constant: c
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct LabelExpression {
+	pub label: String
+}
+
+impl LabelExpression {
+	pub fn new(l: String) -> Self {
+		Self {
+			label: l
+		}
+	}
+}
+
+#[derive(Clone)]
 pub struct LiteralExpression {
-	pub val: f64
+	pub val: f64,
+	// The literal as written in the input, or None for values that were
+	// not, such as the result that a percentage is taken of.
+	pub text: Option<String>
 }
 
 impl LiteralExpression {
     pub fn new(v: f64) -> Self {
 		Self {
-			val: v
+			val: v,
+			text: None
 		}
     }
+	
+	pub fn with_text(v: f64, text: &str) -> Self {
+		Self {
+			val: v,
+			text: Some(String::from(text))
+		}
+	}
+}
+
+// The operation that reduce applies across the values of its body.
+#[derive(Copy, Clone, PartialEq)]
+pub enum ReduceOp {
+	Sum,
+	Product,
+	Max,
+	Min
+}
+
+impl fmt::Display for ReduceOp {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", match self {
+			ReduceOp::Sum => "+",
+			ReduceOp::Product => "*",
+			ReduceOp::Max => "max",
+			ReduceOp::Min => "min"
+		})
+	}
+}
+
+// reduce(op, var, lo, hi, step, body): body evaluated with var bound to
+// lo, lo + step, ... up to hi, and the values combined with op.
+#[derive(Clone)]
+pub struct ReduceExpression {
+	pub op: ReduceOp,
+	pub var: String,
+	pub lo: Expression,
+	pub hi: Expression,
+	pub step: Expression,
+	pub body: Expression
+}
+
+impl ReduceExpression {
+	pub fn new(op: ReduceOp, var: String, lo: Expression, hi: Expression, step: Expression, body: Expression) -> Self {
+		Self { op, var, lo, hi, step, body }
+	}
+}
+
+// integ(var, lo, hi, body): the integral of body over var from lo to hi.
+#[derive(Clone)]
+pub struct IntegralExpression {
+	pub var: String,
+	pub lo: Expression,
+	pub hi: Expression,
+	pub body: Expression
+}
+
+impl IntegralExpression {
+	pub fn new(var: String, lo: Expression, hi: Expression, body: Expression) -> Self {
+		Self { var, lo, hi, body }
+	}
+}
+
+// getenv("NAME"): the value of an environment variable, looked up when
+// evaluated.
+#[derive(Clone)]
+pub struct EnvExpression {
+	pub name: String
+}
+
+impl EnvExpression {
+	pub fn new(name: String) -> Self {
+		Self { name }
+	}
+}
+
+// The value of a command that also has one, like count, which displays
+// the number of variables and stands for it in an expression.
+#[derive(Clone)]
+pub struct CommandValueExpression {
+	pub command: CommandType
+}
+
+impl CommandValueExpression {
+	pub fn new(command: CommandType) -> Self {
+		Self { command }
+	}
 }
 
+// The commands that can be used as values, as in "n = count".
+pub const VALUE_COMMANDS: &[CommandType] = &[CommandType::Count, CommandType::NumSteps];
+
+impl Expression {
+	// The names of the variables the expression refers to, sorted and
+	// without duplicates.
+	pub fn variables(&self) -> BTreeSet<String> {
+		let mut names = BTreeSet::new();
+		self.collect_variables(&mut names);
+		names
+	}
+	
+	fn collect_variables(&self, names: &mut BTreeSet<String>) {
+		match &self {
+			Expression::ParenExpr(e) => e.expr.collect_variables(names),
+			Expression::UnaryExpr(e) => e.expr.collect_variables(names),
+			Expression::BinaryExpr(e) => {
+				e.left.collect_variables(names);
+				e.right.collect_variables(names);
+			},
+			Expression::FunctionExpr(e) => {
+				for arg in &e.args {
+					arg.collect_variables(names);
+				}
+			},
+			Expression::VariableExpr(e) => { names.insert(e.var.clone()); },
+			Expression::ReduceExpr(e) => {
+				e.lo.collect_variables(names);
+				e.hi.collect_variables(names);
+				e.step.collect_variables(names);
+				
+				// The bound variable is not the one of the same name outside.
+				let mut body_names = e.body.variables();
+				body_names.remove(&e.var);
+				names.extend(body_names);
+			},
+			Expression::IntegralExpr(e) => {
+				e.lo.collect_variables(names);
+				e.hi.collect_variables(names);
+				let mut body_names = e.body.variables();
+				body_names.remove(&e.var);
+				names.extend(body_names);
+			},
+			Expression::ConstantExpr(_) | Expression::LabelExpr(_) | Expression::LiteralExpr(_) | Expression::EnvExpr(_) | Expression::CommandValueExpr(_) => {}
+		}
+	}
+}
+
+impl fmt::Display for Expression {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self {
+			Expression::ParenExpr(e) => write!(f, "({})", e.expr),
+			Expression::UnaryExpr(e) => write!(f, "{}{}", e.op, e.expr),
+			Expression::BinaryExpr(e) => write!(f, "{} {} {}", e.left, e.op, e.right),
+			Expression::FunctionExpr(e) => {
+				let args: Vec<String> = e.args.iter().map(|a| a.to_string()).collect();
+				write!(f, "{}({})", e.func, args.join(", "))
+			},
+			Expression::VariableExpr(e) => write!(f, "{}", e.var),
+			Expression::ConstantExpr(e) => write!(f, "{}", e.constant),
+			Expression::LabelExpr(e) => write!(f, "{}", e.label),
+			Expression::LiteralExpr(e) => write!(f, "{}", e.val),
+			Expression::ReduceExpr(e) => write!(f, "reduce({}, {}, {}, {}, {}, {})", e.op, e.var, e.lo, e.hi, e.step, e.body),
+			Expression::IntegralExpr(e) => write!(f, "integ({}, {}, {}, {})", e.var, e.lo, e.hi, e.body),
+			Expression::EnvExpr(e) => write!(f, "getenv(\"{}\")", e.name),
+			Expression::CommandValueExpr(e) => write!(f, "{}", e.command)
+		}
+	}
+}
+
+#[allow(clippy::enum_variant_names)]
 pub enum Statement {
 	CommandStmt(Box<CommandStatement>),
-	AssignmentStmt(Box<AssignmentStatement>)
+	AssignmentStmt(Box<AssignmentStatement>),
+	LabelStmt(Box<LabelStatement>),
+	PercentStmt(Box<PercentStatement>)
+}
+
+pub enum CommandArgument {
+	Empty,
+	Words(Vec<String>),
+	Expr(Expression),
+	// An expression followed by the units to convert its value from and to.
+	Conversion(Expression, String, String),
+	// A file name given as a string.
+	Path(String),
+	// The rest of the line as written, e.g. text to search for.
+	Text(String)
 }
 
 pub struct CommandStatement {
-	pub command: CommandType
+	pub command: CommandType,
+	pub argument: CommandArgument
 }
 
 impl CommandStatement {
-	pub fn new(cmd: CommandType) -> Self {
+	pub fn new(cmd: CommandType, arg: CommandArgument) -> Self {
 		Self {
-			command: cmd
+			command: cmd,
+			argument: arg
 		}
 	}
 }
@@ -124,36 +344,150 @@ impl AssignmentStatement {
 	}
 }
 
+#[derive(Copy, Clone, PartialEq)]
+pub enum Associativity {
+	Left,
+	#[allow(dead_code)] // No operator is right-associative yet.
+	Right
+}
+
+// Binary operators with their precedence (higher binds tighter) and
+// associativity. A new binary operator only needs an entry here.
+// Implicit multiplication, as in "2x", has the precedence of '*'.
+pub const BINARY_OPERATORS: &[(OperatorType, u8, Associativity)] = &[
+	(OperatorType::Less, 0, Associativity::Left),
+	(OperatorType::LessOrEqual, 0, Associativity::Left),
+	(OperatorType::Greater, 0, Associativity::Left),
+	(OperatorType::GreaterOrEqual, 0, Associativity::Left),
+	(OperatorType::Equal, 0, Associativity::Left),
+	(OperatorType::NotEqual, 0, Associativity::Left),
+	(OperatorType::Plus, 1, Associativity::Left),
+	(OperatorType::Minus, 1, Associativity::Left),
+	(OperatorType::Times, 2, Associativity::Left),
+	(OperatorType::DividedBy, 2, Associativity::Left),
+	(OperatorType::FloorDiv, 2, Associativity::Left),
+	(OperatorType::Modulo, 2, Associativity::Left),
+	(OperatorType::Power, 3, Associativity::Left)
+];
+
+pub fn binary_operator(op: OperatorType) -> Option<(u8, Associativity)> {
+	BINARY_OPERATORS.iter()
+		.find(|(o, _, _)| *o == op)
+		.map(|(_, precedence, associativity)| (*precedence, *associativity))
+}
+
+// Split input into statements separated by ';'. A ';' in a comment, which
+// runs to the end of the line, doesn't count.
+pub fn split_statements(text: &str) -> Vec<&str> {
+	let mut statements = Vec::new();
+	let mut start = 0;
+	let mut in_comment = false;
+	
+	for (i, c) in text.char_indices() {
+		match c {
+			'#' => { in_comment = true; },
+			'\n' => { in_comment = false; },
+			';' if !in_comment => {
+				statements.push(&text[start..i]);
+				start = i + 1;
+			},
+			_ => {}
+		}
+	}
+	
+	statements.push(&text[start..]);
+	statements
+}
+
+pub struct LabelStatement {
+	pub label: String,
+	pub expression: Expression
+}
+
+impl LabelStatement {
+	pub fn new(label: String, expr: Expression) -> Self {
+		Self {
+			label,
+			expression: expr
+		}
+	}
+}
+
+// A line like "+10%" or "-5%", which adds that percentage of the latest
+// result to it or subtracts it.
+pub struct PercentStatement {
+	pub op: OperatorType,
+	pub expression: Expression
+}
+
+impl PercentStatement {
+	pub fn new(op: OperatorType, expr: Expression) -> Self {
+		Self {
+			op,
+			expression: expr
+		}
+	}
+}
+
 pub enum Program {
 	Stmt(Box<Statement>),
-	Expr(Box<Expression>)
+	Expr(Box<Expression>),
+	// "!n", to run line n of the input history again.
+	Recall(usize),
+	// Nothing but whitespace and comments.
+	Empty
 }
 
 pub struct Parser<'a> {
 	text: &'a str,
+	labels: Vec<String>,
+	aliases: Vec<(String, FunctionType)>,
 	lexer: Lexer<'a>
 }
 
 impl<'a> Parser<'a> {
 	pub fn new(s: &'a str) -> Self {
+		Self::with_labels(s, Vec::new())
+	}
+	
+	// See Lexer::with_labels().
+	pub fn with_labels(s: &'a str, labels: Vec<String>) -> Self {
+		Self::with_names(s, labels, Vec::new())
+	}
+	
+	// See Lexer::with_names().
+	pub fn with_names(s: &'a str, labels: Vec<String>, aliases: Vec<(String, FunctionType)>) -> Self {
 		Self {
-			text: &s,
+			text: s,
+			labels,
+			aliases,
 			lexer: Lexer::new(s)
 		}
 	}
 	
 	pub fn parse(&mut self) -> ParseResult<Program> {
-		self.lexer = Lexer::new(self.text);
-		self.lexer.get_next()?;
-		self.parse_program()
+		self.lexer = Lexer::with_names(self.text, self.labels.clone(), self.aliases.clone());
+		let result = self.lexer.get_next().and_then(|_| self.parse_program());
+		
+		// An error is found at the token the parser stopped at, unless the
+		// lexer found it first.
+		result.map_err(|e| e.at_column(self.lexer.column()))
 	}
 	
 	fn parse_program(&mut self) -> ParseResult<Program> {
+		if let Some(program) = self.parse_percent_program()? {
+			return Ok(program);
+		}
+		
 		match self.lexer.current()? {
+			Token::Command(cmd) if VALUE_COMMANDS.contains(&cmd) && self.is_value_use(cmd)? => {
+				// A value command used as a value, as in "count + 1".
+				self.parse_expression_program()
+			},
 			Token::Command(_) => {
 				self.parse_command_program()
 			},
-			Token::Variable(_) => {
+			Token::Variable(_) | Token::Constant(_) => {
 				// This is currently the sole reason why we need the
 				// atrocious Lexer::peek_next(): we need to figure out
 				// if we have an assignment or a simple expression, without
@@ -162,23 +496,105 @@ impl<'a> Parser<'a> {
 				// input into a Token vector that can be looked up at will,
 				// but for now, we can live with this.
 				if let Token::Operator(op) = self.lexer.peek_next()? && op == OperatorType::Assignment {
-					return self.parse_assignment_program();
+					self.parse_assignment_program()
 				}
 				else {
-					return self.parse_expression_program();
+					self.parse_expression_program()
 				}
-			}
+			},
+			Token::Label(_) => {
+				if let Token::Operator(OperatorType::Colon) = self.lexer.peek_next()? {
+					self.parse_label_program()
+				} else {
+					self.parse_expression_program()
+				}
+			},
+			Token::Eol => {
+				Ok(Program::Empty)
+			},
+			Token::Operator(OperatorType::Factorial) => {
+				self.parse_recall_program()
+			},
 			_ => {
 				self.parse_expression_program()
 			}
 		}
 	}
 	
+	// A factorial sign has nothing to follow at the start of input, so
+	// there it means recalling a line from the history instead.
+	fn parse_recall_program(&mut self) -> ParseResult<Program> {
+		match self.lexer.get_next()? {
+			Token::Literal(n) if n >= 1.0 && n.fract() == 0.0 => {
+				self.lexer.get_next()?;
+				self.require_end_of_input()?;
+				Ok(Program::Recall(n as usize))
+			},
+			_ => error("expected a line number after '!', e.g. '!3'.")
+		}
+	}
+	
 	fn parse_command_program(&mut self) -> ParseResult<Program> {
 		if let Token::Command(cmd) = self.lexer.current()? {
-			self.lexer.get_next()?;
+			let arg = match cmd {
+				CommandType::Alias | CommandType::AutoRecalc | CommandType::DecimalPoint | CommandType::Del | CommandType::Errors | CommandType::Multiline | CommandType::NumSteps | CommandType::Prec | CommandType::Precision
+						| CommandType::Recompute | CommandType::RoundMode | CommandType::Seed | CommandType::SigFigs | CommandType::Store | CommandType::Vars => {
+					let words = self.lexer.take_remainder().split_whitespace().map(String::from).collect();
+					CommandArgument::Words(words)
+				},
+				CommandType::Assert | CommandType::Bits | CommandType::Peek | CommandType::Step => {
+					self.lexer.get_next()?;
+					CommandArgument::Expr(self.parse_expression()?)
+				},
+				CommandType::Convert => {
+					// Unit names may also be names of functions, e.g. "deg", so
+					// they are taken as words from the end of the line.
+					let remainder = self.lexer.take_remainder();
+					let words: Vec<&str> = remainder.split_whitespace().collect();
+					
+					if words.len() < 3 {
+						return error("convert: expected a value and two units, e.g. 'convert 1 mi km'.");
+					}
+					
+					let (from, to) = (words[words.len() - 2], words[words.len() - 1]);
+					let value = remainder.trim_end().strip_suffix(to).unwrap().trim_end().strip_suffix(from).unwrap();
+					let mut parser = Parser::with_names(value, self.labels.clone(), self.aliases.clone());
+					parser.lexer.get_next()?;
+					let expr = parser.parse_expression()?;
+					parser.require_end_of_input()?;
+					CommandArgument::Conversion(expr, String::from(from), String::from(to))
+				},
+				CommandType::Load | CommandType::Save => {
+					let Token::Text(path) = self.lexer.get_next()? else {
+						let name = if cmd == CommandType::Load { "load" } else { "save" };
+						return error(&format!("{name}: expected a file name in quotes, e.g. '{name} \"session.txt\"'."));
+					};
+					
+					self.lexer.get_next()?;
+					CommandArgument::Path(path)
+				},
+				CommandType::Stat => {
+					// "stat add <expr>" takes an expression, the rest take words.
+					if let Ok(Token::Variable(word)) = self.lexer.peek_next() && word == "add" {
+						self.lexer.get_next()?;
+						self.lexer.get_next()?;
+						CommandArgument::Expr(self.parse_expression()?)
+					} else {
+						let words = self.lexer.take_remainder().split_whitespace().map(String::from).collect();
+						CommandArgument::Words(words)
+					}
+				},
+				CommandType::Search => {
+					CommandArgument::Text(String::from(self.lexer.take_remainder().trim()))
+				},
+				_ => {
+					self.lexer.get_next()?;
+					CommandArgument::Empty
+				}
+			};
+			
 			self.require_end_of_input()?;
-			let stmt = CommandStatement::new(cmd);
+			let stmt = CommandStatement::new(cmd, arg);
 			let stmt = Statement::CommandStmt(Box::new(stmt));
 			let prog = Program::Stmt(Box::new(stmt));
 			Ok(prog)		
@@ -186,18 +602,17 @@ impl<'a> Parser<'a> {
 			panic!("Parser::parse_command_program(): logic error.");
 		}
 	}
-
+	
 	fn parse_assignment_program(&mut self) -> ParseResult<Program> {
 		// These two were already vetted by the caller:
 		let variable = self.lexer.current()?; // This is the variable
 		self.lexer.get_next()?; // This is the assignment operator
 		
-		let var: char;
-		
-		match variable {
-			Token::Variable(v) => { var = v },
+		let var = match variable {
+			Token::Variable(v) => v,
+			Token::Constant(c) => { return error(&format!("cannot assign to constant {}.", c)); },
 			_ => { panic!("Parser::parse_assignment_program(): logic error."); }
-		}
+		};
 		
 		let lhs = VariableExpression::new(var);
 		self.lexer.get_next()?;
@@ -207,7 +622,64 @@ impl<'a> Parser<'a> {
 		let stmt = Statement::AssignmentStmt(Box::new(stmt));
 		Ok(Program::Stmt(Box::new(stmt)))
 	}
-
+	
+	fn parse_label_program(&mut self) -> ParseResult<Program> {
+		// Both the label and the colon were already vetted by the caller.
+		let label = match self.lexer.current()? {
+			Token::Label(l) => l,
+			_ => { panic!("Parser::parse_label_program(): logic error."); }
+		};
+		
+		self.lexer.get_next()?; // This is the colon
+		self.lexer.get_next()?;
+		let expr = self.parse_expression()?;
+		self.require_end_of_input()?;
+		let stmt = Statement::LabelStmt(Box::new(LabelStatement::new(label, expr)));
+		Ok(Program::Stmt(Box::new(stmt)))
+	}
+	
+	// As '%' is otherwise the remainder operator, a line that starts with a
+	// sign and ends with '%' is recognized from the text before tokenizing
+	// it. Return None for any other line.
+	fn parse_percent_program(&mut self) -> ParseResult<Option<Program>> {
+		let text = match self.text.find('#') {
+			Some(comment) => &self.text[..comment],
+			None => self.text
+		}.trim();
+		
+		let op = if text.starts_with('+') {
+			OperatorType::Plus
+		} else if text.starts_with('-') {
+			OperatorType::Minus
+		} else {
+			return Ok(None);
+		};
+		
+		if text.len() < 2 || !text.ends_with('%') {
+			return Ok(None);
+		}
+		
+		let mut parser = Parser::with_names(&text[1..text.len() - 1], self.labels.clone(), self.aliases.clone());
+		parser.lexer.get_next()?;
+		let expr = parser.parse_expression()?;
+		parser.require_end_of_input()?;
+		let stmt = Statement::PercentStmt(Box::new(PercentStatement::new(op, expr)));
+		Ok(Some(Program::Stmt(Box::new(stmt))))
+	}
+	
+	// Whether a value command at the start of the line is followed by an
+	// operator that makes it a value, as in "count + 1". For numsteps, which
+	// also takes an argument, an operator that could start one, as in
+	// "numsteps -1", leaves it a command so that the argument is checked.
+	fn is_value_use(&mut self, cmd: CommandType) -> ParseResult<bool> {
+		let Token::Operator(op) = self.lexer.peek_next()? else {
+			return Ok(false);
+		};
+		
+		let starts_argument = matches!(op, OperatorType::Plus | OperatorType::Minus | OperatorType::LeftParen);
+		Ok(cmd != CommandType::NumSteps || !starts_argument)
+	}
+	
 	fn parse_expression_program(&mut self) -> ParseResult<Program> {
 		let expr = self.parse_expression()?;
 		self.require_end_of_input()?;
@@ -215,83 +687,77 @@ impl<'a> Parser<'a> {
 	}
 	
 	fn parse_expression(&mut self) -> ParseResult<Expression> {
-		self.parse_additive_expression()
+		self.parse_binary_expression(0)
 	}
 	
-	fn parse_additive_expression(&mut self) -> ParseResult<Expression> {
-		let mut result = self.parse_multiplicative_expression()?;
+	// Parse terms joined by binary operators that have at least the given
+	// precedence, using precedence climbing over BINARY_OPERATORS.
+	fn parse_binary_expression(&mut self, min_precedence: u8) -> ParseResult<Expression> {
+		let mut result = self.parse_postfix_expression()?;
 		
 		loop {
-			match self.lexer.current()? {
-				Token::Operator(op) if op == OperatorType::Plus || op == OperatorType::Minus => {
-					self.lexer.get_next()?;
-					let rhs = self.parse_multiplicative_expression()?;
-					result = Expression::BinaryExpr(Box::new(BinaryExpression::new(op, result, rhs)));
+			let (op, implicit) = match self.lexer.current()? {
+				Token::Operator(op) if binary_operator(op).is_some() => (op, false),
+				// Support constructs like "2x", "ax^2", "a(b+c)", "-3sqrt(...", etc
+				Token::Operator(OperatorType::LeftParen) | Token::Variable(_) | Token::Constant(_)
+						| Token::Label(_) | Token::Function(_) => {
+					(OperatorType::Times, true)
 				},
 				_ => {
 					break;
 				}
+			};
+			
+			let (precedence, associativity) = binary_operator(op).expect("Parser::parse_binary_expression(): logic error.");
+			
+			if precedence < min_precedence {
+				break;
 			}
-		}
-		
-		Ok(result)
-	}
-	
-	fn parse_multiplicative_expression(&mut self) -> ParseResult<Expression> {
-		let mut result = self.parse_power_expression()?;
-		
-		loop {
-			match self.lexer.current()? {
-				Token::Operator(op) if op == OperatorType::Times || op == OperatorType::DividedBy => {
-					self.lexer.get_next()?;
-					let rhs = self.parse_power_expression()?;
-					result = Expression::BinaryExpr(Box::new(BinaryExpression::new(op, result, rhs)));
-				},
-				// Support constructs like a(b+c)
-				Token::Operator(OperatorType::LeftParen) => {
-					let rhs = self.parse_term()?;
-					result = Expression::BinaryExpr(Box::new(BinaryExpression::new(OperatorType::Times, result, rhs)));
-				}
-				// Support constructs like "2x", "ax^2", "-3sqrt(...", etc
-				Token::Variable(_) | Token::Function(_) => {
-					let rhs = self.parse_power_expression()?;
-					result = Expression::BinaryExpr(Box::new(BinaryExpression::new(OperatorType::Times, result, rhs)));
-				},
-				_ => {
-					break;
-				}
+			
+			if !implicit {
+				self.lexer.get_next()?;
 			}
+			
+			let rhs = match associativity {
+				Associativity::Left => self.parse_binary_expression(precedence + 1)?,
+				Associativity::Right => self.parse_binary_expression(precedence)?
+			};
+			
+			result = Expression::BinaryExpr(Box::new(BinaryExpression::new(op, result, rhs)));
 		}
 		
 		Ok(result)
 	}
 	
-	fn parse_power_expression(&mut self) -> ParseResult<Expression> {
+	// Parse a term followed by any number of postfix factorial operators,
+	// which bind tighter than any binary operator: "3!^2" is (3!)^2. As "n!"
+	// is just another way of writing "fact(n)", it is parsed as such.
+	fn parse_postfix_expression(&mut self) -> ParseResult<Expression> {
 		let mut result = self.parse_term()?;
 		
-		loop {
-			if let Token::Operator(OperatorType::Power) = self.lexer.current()? {
-				self.lexer.get_next()?;
-				let rhs = self.parse_term()?;
-				result = Expression::BinaryExpr(Box::new(BinaryExpression::new(OperatorType::Power, result, rhs)));
-			} else {
-				break;
-			}
+		while let Token::Operator(OperatorType::Factorial) = self.lexer.current()? {
+			self.lexer.get_next()?;
+			result = Expression::FunctionExpr(Box::new(FunctionExpression::new(FunctionType::Fact, vec![result])));
 		}
 		
 		Ok(result)
 	}
-
+	
 	fn parse_term(&mut self) -> ParseResult<Expression> {
 		// Parse a top-precedence subexpression, i.e., a paren-expression, a unary operation,
 		// a function invocation, a variable or a literal.
 		match self.lexer.current()? {
-			Token::Command(_) => { 
-				error(&format!("unexpected command {}", "TODO"))
+			Token::Command(cmd) if VALUE_COMMANDS.contains(&cmd) => {
+				self.lexer.get_next()?;
+				Ok(Expression::CommandValueExpr(Box::new(CommandValueExpression::new(cmd))))
+			},
+			Token::Command(cmd) => {
+				error(&format!("unexpected command {cmd}; expected a value."))
 			},
 			Token::Literal(val) => {
+				let literal = LiteralExpression::with_text(val, self.lexer.literal_text());
 				self.lexer.get_next()?;
-				Ok(Expression::LiteralExpr(Box::new(LiteralExpression::new(val))))
+				Ok(Expression::LiteralExpr(Box::new(literal)))
 			},
 			Token::Operator(op) => {
 				if op == OperatorType::LeftParen {
@@ -300,17 +766,48 @@ impl<'a> Parser<'a> {
 					self.require_operator(OperatorType::RightParen)?;
 					Ok(Expression::ParenExpr(Box::new(ParenExpression::new(expr))))
 				} else if op == OperatorType::Plus || op == OperatorType::Minus {
+					// Exponentiation binds tighter than a unary sign, so that
+					// "-2^2" is -(2^2), but nothing else does: "-2x" is (-2)x.
 					self.lexer.get_next()?;
-					let expr = self.parse_term()?;
+					let (power, _) = binary_operator(OperatorType::Power).expect("Parser::parse_term(): logic error.");
+					let expr = self.parse_binary_expression(power)?;
 					Ok(Expression::UnaryExpr(Box::new(UnaryExpression::new(op, expr))))
 				} else {
-					error("")
+					error(&format!("unexpected operator '{op}'; expected a value."))
 				}
 			},
 			Token::Variable(var) => {
 				self.lexer.get_next()?;
 				Ok(Expression::VariableExpr(Box::new(VariableExpression::new(var))))
 			},
+			Token::Constant(constant) => {
+				self.lexer.get_next()?;
+				Ok(Expression::ConstantExpr(Box::new(ConstantExpression::new(constant))))
+			},
+			Token::Label(label) => {
+				self.lexer.get_next()?;
+				Ok(Expression::LabelExpr(Box::new(LabelExpression::new(label))))
+			},
+			Token::Function(FunctionType::Reduce) => {
+				self.lexer.get_next()?;
+				self.parse_reduce()
+			},
+			Token::Function(FunctionType::Integ) => {
+				self.lexer.get_next()?;
+				self.parse_integral()
+			},
+			Token::Function(FunctionType::GetEnv) => {
+				self.lexer.get_next()?;
+				self.require_operator(OperatorType::LeftParen)?;
+				
+				let Token::Text(name) = self.lexer.current()? else {
+					return error("getenv: expected the name of an environment variable in quotes, e.g. 'getenv(\"SCALE\")'.");
+				};
+				
+				self.lexer.get_next()?;
+				self.require_operator(OperatorType::RightParen)?;
+				Ok(Expression::EnvExpr(Box::new(EnvExpression::new(name))))
+			},
 			Token::Function(func) => {
 				self.lexer.get_next()?;
 				self.require_operator(OperatorType::LeftParen)?;
@@ -318,22 +815,76 @@ impl<'a> Parser<'a> {
 				self.require_operator(OperatorType::RightParen)?;
 				Ok(Expression::FunctionExpr(Box::new(FunctionExpression::new(func, args))))
 			},
+			Token::Text(text) => error(&format!("unexpected string \"{text}\"; only getenv takes a string.")),
 			Token::Eol => error("unexpected end of input."),
 		}
 	}
 	
+	// The first two arguments of reduce are an operation and a variable
+	// rather than expressions, as in "reduce(+, i, 1, 5, 1, i^2)".
+	fn parse_reduce(&mut self) -> ParseResult<Expression> {
+		self.require_operator(OperatorType::LeftParen)?;
+		
+		let op = match self.lexer.current()? {
+			Token::Operator(OperatorType::Plus) => ReduceOp::Sum,
+			Token::Operator(OperatorType::Times) => ReduceOp::Product,
+			Token::Function(FunctionType::Max) => ReduceOp::Max,
+			Token::Function(FunctionType::Min) => ReduceOp::Min,
+			_ => { return error("reduce: expected +, *, max or min as the operation."); }
+		};
+		
+		self.lexer.get_next()?;
+		self.require_operator(OperatorType::Comma)?;
+		
+		let var = match self.lexer.current()? {
+			Token::Variable(var) if var != ANS => var,
+			_ => { return error("reduce: expected a variable after the operation."); }
+		};
+		
+		self.lexer.get_next()?;
+		self.require_operator(OperatorType::Comma)?;
+		let args = self.parse_expression_list()?;
+		self.require_operator(OperatorType::RightParen)?;
+		
+		match <[Expression; 4]>::try_from(args) {
+			Ok([lo, hi, step, body]) => Ok(Expression::ReduceExpr(Box::new(ReduceExpression::new(op, var, lo, hi, step, body)))),
+			Err(_) => error("reduce: expected an operation, a variable, a range, a step and an expression, e.g. 'reduce(+, i, 1, 5, 1, i^2)'.")
+		}
+	}
+	
+	// The first argument of integ is the variable of integration, as in
+	// "integ(x, 0, 1, x^2)".
+	fn parse_integral(&mut self) -> ParseResult<Expression> {
+		self.require_operator(OperatorType::LeftParen)?;
+		
+		let var = match self.lexer.current()? {
+			Token::Variable(var) if var != ANS => var,
+			_ => { return error("integ: expected a variable as the first argument."); }
+		};
+		
+		self.lexer.get_next()?;
+		self.require_operator(OperatorType::Comma)?;
+		let args = self.parse_expression_list()?;
+		self.require_operator(OperatorType::RightParen)?;
+		
+		match <[Expression; 3]>::try_from(args) {
+			Ok([lo, hi, body]) => Ok(Expression::IntegralExpr(Box::new(IntegralExpression::new(var, lo, hi, body)))),
+			Err(_) => error("integ: expected a variable, a range and an expression, e.g. 'integ(x, 0, 1, x^2)'.")
+		}
+	}
+	
 	fn parse_expression_list(&mut self) -> ParseResult<Vec<Expression>> {
 		let mut args = Vec::<Expression>::new();
-		let mut done = if let Token::Operator(OperatorType::RightParen) = self.lexer.current()? { true } else { false };
+		let mut done = matches!(self.lexer.current()?, Token::Operator(OperatorType::RightParen));
 		
 		while !done {
 			args.push(self.parse_expression()?);
 			
 			match self.lexer.current()? {
-				Token::Operator(o) if o == OperatorType::RightParen => {
+				Token::Operator(OperatorType::RightParen) => {
 					done = true; // Leave paren in place for the caller
  				},
-				Token::Operator(o) if o == OperatorType::Comma => {
+				Token::Operator(OperatorType::Comma) => {
 					self.lexer.get_next()?; // Consume
 				},
 				_ => {
@@ -341,10 +892,10 @@ impl<'a> Parser<'a> {
 				}
 			}
 		}
-				
+		
 		Ok(args)
 	}
-
+	
 	// Require and consume.
 	fn require_operator(&mut self, t: OperatorType) -> ParseResult<Token> {
 		let token = self.lexer.current()?;
@@ -356,7 +907,7 @@ impl<'a> Parser<'a> {
 			error("")
 		}
 	}
-
+	
 	fn require_end_of_input(&mut self) -> ParseResult<Token> {
 		let token = self.lexer.current()?;
 		
@@ -377,3 +928,347 @@ mod utility {
 		Err(Error::new(&format!("Parse error: {}", description)))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn expression_is_displayed_as_source() {
+		assert_eq!(render("2+3*x"), "2 + 3 * x");
+		assert_eq!(render("-(a - b)^2"), "-(a - b) ^ 2");
+		assert_eq!(render("max(1, sqrt(4), -x)"), "max(1, sqrt(4), -x)");
+		assert_eq!(render("2a(b+1)"), "2 * a * (b + 1)");
+	}
+	
+	#[test]
+	fn statements_are_split_at_semicolons() {
+		assert_eq!(split_statements("a = 2; b = 3;a+b"), vec!["a = 2", " b = 3", "a+b"]);
+		assert_eq!(split_statements("x"), vec!["x"]);
+		assert_eq!(split_statements("x;"), vec!["x", ""]);
+		assert_eq!(split_statements("x # y; z\n+1; 2"), vec!["x # y; z\n+1", " 2"]);
+	}
+	
+	#[test]
+	fn variables_of_expression_are_collected() {
+		let variables = |line: &str| match Parser::new(line).parse().expect("expression doesn't parse!") {
+			Program::Expr(expr) => expr.variables().into_iter().collect::<Vec<String>>(),
+			_ => { panic!("not an expression!"); }
+		};
+		
+		assert_eq!(variables("b*2 + max(a, -(c + b)) + pi"), vec!["a", "b", "c"]);
+		assert!(variables("2 + sqrt(4)").is_empty());
+	}
+	
+	#[test]
+	fn command_takes_words_as_argument() {
+		let mut parser = Parser::new("vars  def ");
+		
+		match parser.parse().expect("command doesn't parse!") {
+			Program::Stmt(stmt) => match *stmt {
+				Statement::CommandStmt(cmd) => match &cmd.argument {
+					CommandArgument::Words(words) => { assert_eq!(words, &vec![String::from("def")]); },
+					_ => { panic!("command has no words!"); }
+				},
+				_ => { panic!("not a command!"); }
+			},
+			_ => { panic!("not a statement!"); }
+		}
+	}
+	
+	#[test]
+	fn operators_group_by_precedence_and_associativity() {
+		// These are the trees built by the earlier parser that had one
+		// function per precedence level.
+		let expected = [
+			("1+2*3", "[1 + [2 * 3]]"),
+			("1*2+3", "[[1 * 2] + 3]"),
+			("1-2-3", "[[1 - 2] - 3]"),
+			("8/4/2", "[[8 / 4] / 2]"),
+			("2^3^2", "[[2 ^ 3] ^ 2]"),
+			("-2^2", "-[2 ^ 2]"),
+			("1+-2", "[1 + -2]"),
+			("-(1+2)*3", "[-([1 + 2]) * 3]"),
+			("2*-3^2", "[2 * -[3 ^ 2]]"),
+			("2^-3^2", "[2 ^ -[3 ^ 2]]"),
+			("-2x^2", "[-2 * [x ^ 2]]"),
+			("4*10^3+3*10^2", "[[4 * [10 ^ 3]] + [3 * [10 ^ 2]]]"),
+			("max(1,2)^2", "[max(1, 2) ^ 2]")
+		];
+		
+		for (line, tree) in expected {
+			assert_eq!(structure_of(line), tree, "{}", line);
+		}
+	}
+	
+	#[test]
+	fn implicit_multiplication_groups_like_times() {
+		let expected = [
+			("2x^2", "[2 * [x ^ 2]]"),
+			("2x y", "[[2 * x] * y]"),
+			("a x^2", "[a * [x ^ 2]]"),
+			// A run of letters is a single variable, not a product.
+			("2ab", "[2 * ab]"),
+			("2ab^2", "[2 * [ab ^ 2]]"),
+			("(2x)^2", "[([2 * x]) ^ 2]"),
+			("2.5e1x^2", "[25 * [x ^ 2]]"),
+			("a/b c", "[[a / b] * c]"),
+			("2^x y", "[[2 ^ x] * y]"),
+			("6/2(1+2)", "[[6 / 2] * ([1 + 2])]"),
+			("-3sqrt(4)x", "[[-3 * sqrt(4)] * x]"),
+			("a(b+c)", "[a * ([b + c])]"),
+			("sin(x)cos(y)", "[sin(x) * cos(y)]"),
+			// This used to be rejected, as a parenthesized right hand
+			// operand could not be followed by '^'.
+			("2(1+2)^2", "[2 * [([1 + 2]) ^ 2]]")
+		];
+		
+		for (line, tree) in expected {
+			assert_eq!(structure_of(line), tree, "{}", line);
+		}
+	}
+	
+	#[test]
+	fn label_definition_is_parsed() {
+		match Parser::new("zero: 2x").parse().expect("label doesn't parse!") {
+			Program::Stmt(stmt) => match *stmt {
+				Statement::LabelStmt(l) => {
+					assert_eq!(l.label, "zero");
+					assert_eq!(l.expression.to_string(), "2 * x");
+				},
+				_ => { panic!("not a label!"); }
+			},
+			_ => { panic!("not a statement!"); }
+		}
+	}
+	
+	#[test]
+	fn known_label_is_parsed_as_operand() {
+		let mut parser = Parser::with_labels("2zero + z", vec![String::from("zero")]);
+		
+		match parser.parse().expect("expression doesn't parse!") {
+			Program::Expr(expr) => { assert_eq!(structure(&expr), "[[2 * zero] + z]"); },
+			_ => { panic!("not an expression!"); }
+		}
+	}
+	
+	#[test]
+	fn comparisons_bind_loosest() {
+		assert_eq!(structure_of("1 + 2 < 2 * 3"), "[[1 + 2] < [2 * 3]]");
+		assert_eq!(structure_of("2^3 == 8"), "[[2 ^ 3] == 8]");
+		assert_eq!(structure_of("a <= b != c >= d"), "[[[a <= b] != c] >= d]");
+		assert_eq!(structure_of("3! != 6"), "[fact(3) != 6]");
+		assert_eq!(structure_of("x > -1"), "[x > -1]");
+	}
+	
+	#[test]
+	fn factorial_binds_tightest() {
+		assert_eq!(structure_of("5!"), "fact(5)");
+		assert_eq!(structure_of("3!^2"), "[fact(3) ^ 2]");
+		assert_eq!(structure_of("2^3!"), "[2 ^ fact(3)]");
+		assert_eq!(structure_of("-3!"), "-fact(3)");
+		assert_eq!(structure_of("2x!"), "[2 * fact(x)]");
+		assert_eq!(structure_of("3!!"), "fact(fact(3))");
+		assert_eq!(structure_of("(1+2)!"), "fact(([1 + 2]))");
+		assert!(Parser::new("!x").parse().is_err());
+	}
+	
+	#[test]
+	fn percent_of_latest_result_is_parsed() {
+		match Parser::new("+ 2*5%  # tip").parse().expect("percent doesn't parse!") {
+			Program::Stmt(stmt) => match *stmt {
+				Statement::PercentStmt(p) => {
+					assert!(p.op == OperatorType::Plus);
+					assert_eq!(structure(&p.expression), "[2 * 5]");
+				},
+				_ => { panic!("not a percentage!"); }
+			},
+			_ => { panic!("not a statement!"); }
+		}
+		
+		assert_eq!(structure_of("-5 % 3"), "[-5 % 3]");
+		assert!(Parser::new("+%").parse().is_err());
+		assert!(Parser::new("-5%%").parse().is_err());
+	}
+	
+	#[test]
+	fn operator_in_place_of_value_is_reported() {
+		for (line, op) in [("2 + * 3", "*"), ("* 5", "*"), ("/ 2", "/"), ("2^//3", "//"), ("max(1, )", ")")] {
+			match Parser::new(line).parse() {
+				Err(e) => { assert_eq!(e.description, format!("Parse error: unexpected operator '{op}'; expected a value."), "{}", line); },
+				Ok(_) => { panic!("{} was accepted!", line); }
+			}
+		}
+	}
+	
+	#[test]
+	fn error_is_located_where_parsing_stopped() {
+		for (line, column) in [("2 + * 3", 4), ("max(1, )", 7), ("1 +", 3), ("(1 + 2) 3", 8), ("x = 1 $", 6)] {
+			match Parser::new(line).parse() {
+				Err(e) => { assert_eq!(e.column, Some(column), "{}", line); },
+				Ok(_) => { panic!("{} was accepted!", line); }
+			}
+		}
+	}
+	
+	#[test]
+	fn assignment_to_constant_is_rejected() {
+		match Parser::new("pi = 3").parse() {
+			Err(e) => { assert_eq!(e.description, "Parse error: cannot assign to constant pi."); },
+			Ok(_) => { panic!("assignment to a constant was accepted!"); }
+		}
+		
+		assert!(Parser::new("e = 2").parse().is_err());
+		assert_eq!(structure_of("2pi"), "[2 * pi]");
+	}
+	
+	#[test]
+	fn trailing_comment_is_not_extra_input() {
+		assert_eq!(structure_of("2 + 2 # this is four"), "[2 + 2]");
+		assert!(Parser::new("a = 2 # setup").parse().is_ok());
+		
+		match Parser::new("precision 3 # decimals").parse().expect("command doesn't parse!") {
+			Program::Stmt(stmt) => match *stmt {
+				Statement::CommandStmt(cmd) => match cmd.argument {
+					CommandArgument::Words(words) => { assert_eq!(words, vec![String::from("3")]); },
+					_ => { panic!("command has no words!"); }
+				},
+				_ => { panic!("not a command!"); }
+			},
+			_ => { panic!("not a statement!"); }
+		}
+	}
+	
+	#[test]
+	fn adjacent_literals_are_rejected() {
+		assert!(Parser::new("2 3").parse().is_err());
+	}
+	
+	#[test]
+	fn line_number_after_factorial_sign_is_recall() {
+		assert!(matches!(Parser::new("!3").parse(), Ok(Program::Recall(3))));
+		assert!(matches!(Parser::new(" !12 # again").parse(), Ok(Program::Recall(12))));
+		
+		for line in ["!", "!0", "!1.5", "!x", "!3 + 1", "3!2"] {
+			assert!(Parser::new(line).parse().is_err(), "{}", line);
+		}
+	}
+	
+	#[test]
+	fn blank_input_is_an_empty_program() {
+		for line in ["", "   ", "\t\n", "# a comment", "  # another\n"] {
+			assert!(matches!(Parser::new(line).parse(), Ok(Program::Empty)), "{:?}", line);
+		}
+	}
+	
+	#[test]
+	fn reduce_takes_an_operation_and_a_variable() {
+		assert_eq!(render("reduce(+, i, 1, n, 1, i^2)"), "reduce(+, i, 1, n, 1, i ^ 2)");
+		assert_eq!(render("reduce(max, x, 0, 1, 0.1, sin(x))"), "reduce(max, x, 0, 1, 0.1, sin(x))");
+		
+		let expected_errors = [
+			("reduce(-, i, 1, 5, 1, i)", "Parse error: reduce: expected +, *, max or min as the operation."),
+			("reduce(+, 2, 1, 5, 1, i)", "Parse error: reduce: expected a variable after the operation."),
+			("reduce(+, ans, 1, 5, 1, ans)", "Parse error: reduce: expected a variable after the operation."),
+			("reduce(+, i, 1, 5, i)", "Parse error: reduce: expected an operation, a variable, a range, a step and an expression, e.g. 'reduce(+, i, 1, 5, 1, i^2)'.")
+		];
+		
+		for (line, expected) in expected_errors {
+			match Parser::new(line).parse() {
+				Err(e) => { assert_eq!(e.description, expected, "{}", line); },
+				Ok(_) => { panic!("{} should not parse!", line); }
+			}
+		}
+	}
+	
+	#[test]
+	fn getenv_takes_a_string() {
+		assert_eq!(render("2 getenv(\"SCALE\")"), "2 * getenv(\"SCALE\")");
+		
+		let expected_errors = [
+			("getenv(scale)", "Parse error: getenv: expected the name of an environment variable in quotes, e.g. 'getenv(\"SCALE\")'."),
+			("1 + \"A\"", "Parse error: unexpected string \"A\"; only getenv takes a string.")
+		];
+		
+		for (line, expected) in expected_errors {
+			match Parser::new(line).parse() {
+				Err(e) => { assert_eq!(e.description, expected, "{}", line); },
+				Ok(_) => { panic!("{} should not parse!", line); }
+			}
+		}
+	}
+	
+	#[test]
+	fn integ_takes_a_variable() {
+		assert_eq!(render("integ(x, 0, a, x^2)"), "integ(x, 0, a, x ^ 2)");
+		
+		let expected_errors = [
+			("integ(2, 0, 1, x)", "Parse error: integ: expected a variable as the first argument."),
+			("integ(x, 0, 1)", "Parse error: integ: expected a variable, a range and an expression, e.g. 'integ(x, 0, 1, x^2)'.")
+		];
+		
+		for (line, expected) in expected_errors {
+			match Parser::new(line).parse() {
+				Err(e) => { assert_eq!(e.description, expected, "{}", line); },
+				Ok(_) => { panic!("{} should not parse!", line); }
+			}
+		}
+		
+		match Parser::new("integ(x, a, b, x * c)").parse().expect("expression doesn't parse!") {
+			Program::Expr(expr) => {
+				let names: Vec<String> = expr.variables().into_iter().collect();
+				assert_eq!(names, vec!["a", "b", "c"]);
+			},
+			_ => { panic!("not an expression!"); }
+		}
+	}
+	
+	#[test]
+	fn bound_variable_of_reduce_is_not_a_dependency() {
+		match Parser::new("reduce(+, i, a, b, 1, i * c) + i").parse().expect("expression doesn't parse!") {
+			Program::Expr(expr) => {
+				let names: Vec<String> = expr.variables().into_iter().collect();
+				assert_eq!(names, vec!["a", "b", "c", "i"]);
+			},
+			_ => { panic!("not an expression!"); }
+		}
+		
+		match Parser::new("reduce(+, i, a, b, 1, i * c)").parse().expect("expression doesn't parse!") {
+			Program::Expr(expr) => {
+				let names: Vec<String> = expr.variables().into_iter().collect();
+				assert_eq!(names, vec!["a", "b", "c"]);
+			},
+			_ => { panic!("not an expression!"); }
+		}
+	}
+	
+	fn structure_of(line: &str) -> String {
+		match Parser::new(line).parse().expect("expression doesn't parse!") {
+			Program::Expr(expr) => structure(&expr),
+			_ => { panic!("not an expression!"); }
+		}
+	}
+	
+	// Render the tree shape with every binary node bracketed.
+	fn structure(expr: &Expression) -> String {
+		match expr {
+			Expression::ParenExpr(e) => format!("({})", structure(&e.expr)),
+			Expression::UnaryExpr(e) => format!("{}{}", e.op, structure(&e.expr)),
+			Expression::BinaryExpr(e) => format!("[{} {} {}]", structure(&e.left), e.op, structure(&e.right)),
+			Expression::FunctionExpr(e) => {
+				let args: Vec<String> = e.args.iter().map(structure).collect();
+				format!("{}({})", e.func, args.join(", "))
+			},
+			_ => expr.to_string()
+		}
+	}
+	
+	fn render(line: &str) -> String {
+		let mut parser = Parser::new(line);
+		
+		match parser.parse().expect("expression doesn't parse!") {
+			Program::Expr(expr) => expr.to_string(),
+			_ => { panic!("not an expression!"); }
+		}
+	}
+}
@@ -0,0 +1,73 @@
+// A small pseudorandom number generator for functions like choose. It is
+// SplitMix64, which is fast and good enough for anything but cryptography,
+// and reproducible: the seed command makes the same choices come out again.
+
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+pub struct Random {
+	state: u64
+}
+
+impl Random {
+	pub fn new(seed: u64) -> Self {
+		Self {
+			state: seed
+		}
+	}
+	
+	// Seeded from the clock, for different choices on every run.
+	pub fn from_time() -> Self {
+		let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+		Self::new(nanos as u64)
+	}
+	
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+		z ^ (z >> 31)
+	}
+	
+	// A number from 0 to n - 1, each as likely as any other, apart from a
+	// bias too small to matter for any n that fits in memory.
+	pub fn below(&mut self, n: usize) -> usize {
+		((self.next_u64() as u128 * n as u128) >> 64) as usize
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn same_seed_gives_same_numbers() {
+		let mut a = Random::new(42);
+		let mut b = Random::new(42);
+		let mut c = Random::new(43);
+		let first: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+		assert_eq!(first, (0..10).map(|_| b.next_u64()).collect::<Vec<u64>>());
+		assert_ne!(first, (0..10).map(|_| c.next_u64()).collect::<Vec<u64>>());
+	}
+	
+	#[test]
+	fn known_sequence_is_produced() {
+		// The first outputs of SplitMix64 seeded with 0.
+		let mut random = Random::new(0);
+		assert_eq!(random.next_u64(), 0xe220a8397b1dcdaf);
+		assert_eq!(random.next_u64(), 0x6e789e6aa1b965f4);
+	}
+	
+	#[test]
+	fn below_covers_range() {
+		let mut random = Random::new(7);
+		let mut seen = [0; 5];
+		
+		for _ in 0..1000 {
+			seen[random.below(5)] += 1;
+		}
+		
+		assert!(seen.iter().all(|&count| count > 150), "{:?}", seen);
+	}
+}
@@ -1,22 +1,267 @@
+use crate::completion::complete;
+use crate::errors::Error;
 use crate::help::help;
 use crate::lexer::*;
+use crate::line_editor::LineEditor;
 use crate::parser::*;
 use crate::Program::*;
 use crate::Statement::*;
 use crate::evaluation::*;
+use crate::format::*;
+use crate::random::Random;
+use crate::significance::*;
+use crate::units::*;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::io::Read;
+use std::io::Write;
 
-pub struct Runner {
-	variables: HashMap<char, f64>
+// How deeply a recalled line may recall another one, as in "!2" when line
+// 2 is "!1". A line recalling itself would otherwise do so forever.
+const MAX_RECALL_DEPTH: usize = 10;
+
+pub struct Runner<W: Write> {
+	context: Context,
+	definitions: HashMap<String, Expression>,
+	// Names given to functions with the alias command, sorted by name.
+	aliases: Vec<(String, FunctionType)>,
+	// Whether assigning a variable also recomputes the ones that depend on it.
+	autorecalc: bool,
+	format: Format,
+	// Whether results computed from number literals are displayed rounded
+	// to their significant figures.
+	sigfigs: bool,
+	// The expression that the latest result, context.ans, was computed from.
+	last_expression: Option<Expression>,
+	// In multiline mode, input lines are collected here until a blank line.
+	multiline: bool,
+	pending: Vec<String>,
+	// Values collected with "stat add".
+	dataset: Vec<f64>,
+	// Input lines in the order they were run, for history and recall.
+	history: Vec<String>,
+	// How many recalls are running, one within another.
+	recall_depth: usize,
+	// Whether syntax and parse errors are displayed with the line and a
+	// caret under where they were found.
+	verbose_errors: bool,
+	// Whether the latest statement failed with an error.
+	failed: bool,
+	// The number of assert commands that have failed.
+	failed_assertions: usize,
+	output: W
 }
 
-impl Runner {
+impl Runner<io::Stdout> {
 	pub fn new() -> Self {
+		Self::with_output(io::stdout())
+	}
+}
+
+impl<W: Write> Runner<W> {
+	pub fn with_output(output: W) -> Self {
 		Self {
-			variables: HashMap::<char, f64>::new()
+			context: Context::new(),
+			definitions: HashMap::<String, Expression>::new(),
+			aliases: Vec::new(),
+			autorecalc: false,
+			format: Format::new(),
+			sigfigs: false,
+			last_expression: None,
+			multiline: false,
+			pending: Vec::new(),
+			dataset: Vec::new(),
+			history: Vec::new(),
+			recall_depth: 0,
+			verbose_errors: false,
+			failed: false,
+			failed_assertions: 0,
+			output
+		}
+	}
+	
+	// Parse and run a single line of input. In multiline mode, lines are
+	// collected until a blank line and then run together, as if the line
+	// breaks were spaces. Return false when it's time to exit.
+	pub fn run_line(&mut self, line: &str) -> bool {
+		let keep_going = if !self.multiline {
+			self.run_input(line)
+		} else if !line.trim().is_empty() {
+			self.pending.push(String::from(line.trim_end()));
+			true
+		} else {
+			self.run_pending()
+		};
+		
+		// Added only once run, so that "!n" can only recall earlier lines.
+		if !line.trim().is_empty() {
+			self.history.push(String::from(line.trim()));
+		}
+		
+		keep_going
+	}
+	
+	fn run_pending(&mut self) -> bool {
+		if self.pending.is_empty() {
+			return true;
+		}
+		
+		let input = self.pending.join("\n");
+		self.pending.clear();
+		self.run_input(&input)
+	}
+	
+	// Run statements separated by ';' in order, stopping at the first one
+	// that fails. Each is parsed only once the ones before it have run, as
+	// they may define labels that it refers to.
+	fn run_input(&mut self, input: &str) -> bool {
+		for statement in split_statements(input) {
+			self.failed = false;
+			let mut parser = Parser::with_names(statement, self.label_names(), self.aliases.clone());
+		
+			match parser.parse() {
+				Ok(program) => {
+					if !self.run(&program) {
+						return false;
+					}
+				},
+				Err(e) => {
+					let text = self.render_error(&e, statement);
+					self.fail(&text);
+				}
+			}
+			
+			if self.failed {
+				break;
+			}
+		}
+		
+		true
+	}
+	
+	// Run an expression given on the command line instead of starting an
+	// interactive session, and return the exit code: 1 if it failed, and 0
+	// otherwise. Input pending in multiline mode, as set by a script, runs
+	// right away.
+	pub fn run_once(&mut self, line: &str) -> i32 {
+		self.failed = false;
+		
+		if self.run_line(line) {
+			self.run_pending();
+		}
+		
+		if self.failed { 1 } else { 0 }
+	}
+	
+	// Run lines read from input until quit or the end of input. The end of
+	// input also ends multiline input, like a blank line.
+	pub fn run_stream<R: BufRead>(&mut self, mut input: R) -> Result<(), Error> {
+		let mut line = String::new();
+		
+		loop {
+			line.clear();
+			
+			match input.read_line(&mut line) {
+				Ok(0) => {
+					self.run_pending();
+					return Ok(());
+				},
+				Ok(_) => {
+					if !self.run_line(&line) {
+						return Ok(());
+					}
+				},
+				Err(e) => {
+					return Err(Error::new(&format!("input error: {}", e)));
+				}
+			}
+		}
+	}
+	
+	// Run lines typed in with line editing, like run_stream.
+	pub fn run_editor<R: Read, V: Write>(&mut self, editor: &mut LineEditor<R, V>) -> Result<(), Error> {
+		loop {
+			match editor.read_line(&self.history, |line| self.complete(line)) {
+				Ok(None) => {
+					self.run_pending();
+					return Ok(());
+				},
+				Ok(Some(line)) => {
+					if !self.run_line(&line) {
+						return Ok(());
+					}
+				},
+				Err(e) => {
+					return Err(Error::new(&format!("input error: {}", e)));
+				}
+			}
+		}
+	}
+	
+	// Run the lines of a script file as if they were typed in. Return false
+	// if the script asked to exit.
+	pub fn load(&mut self, path: &str) -> Result<bool, Error> {
+		match fs::read_to_string(path) {
+			Ok(script) => Ok(self.run_script(&script)),
+			Err(e) => Err(Error::new(&format!("cannot read {}: {}", path, e)))
+		}
+	}
+	
+	// Like run_line for each line, but blank lines and lines starting with
+	// '#' are skipped so that scripts can be commented. In multiline mode,
+	// blank lines end the input as usual, as does the end of the script.
+	pub fn run_script(&mut self, script: &str) -> bool {
+		for line in script.lines() {
+			let trimmed = line.trim();
+			
+			if trimmed.starts_with('#') || (trimmed.is_empty() && !self.multiline) {
+				continue;
+			}
+			
+			if !self.run_line(line) {
+				return false;
+			}
+		}
+		
+		self.run_pending()
+	}
+	
+	// Run an assignment without echoing it, e.g., for --set on the command line.
+	pub fn preset(&mut self, line: &str) -> Result<(), Error> {
+		let mut parser = Parser::new(line);
+		
+		if let Stmt(statement) = parser.parse()? && let AssignmentStmt(assignment) = *statement {
+			self.assign(&assignment)?;
+			Ok(())
+		} else {
+			Err(Error::new(&format!("not an assignment: {}", line.trim())))
 		}
 	}
 	
+	// The input lines run so far, oldest first, e.g., to be saved for the
+	// next session.
+	pub fn history(&self) -> &[String] {
+		&self.history
+	}
+	
+	// Start with the input lines of an earlier session, so that they can
+	// be recalled with !n.
+	pub fn set_history(&mut self, lines: Vec<String>) {
+		self.history = lines;
+	}
+	
+	pub fn failed_assertions(&self) -> usize {
+		self.failed_assertions
+	}
+	
+	// Return the variables that have been assigned but never used, sorted by name.
+	pub fn unused_variables(&self) -> Vec<String> {
+		self.context.unread_variables()
+	}
+	
 	// Return false when it's time to exit.
 	pub fn run(&mut self, program: &Program) -> bool {
 		match program {
@@ -25,55 +270,1948 @@ impl Runner {
 			},
 			Expr(expression) => {
 				self.run_expression(expression)
-			}
+			},
+			Recall(n) => {
+				self.run_recall(*n)
+			},
+			Empty => true
 		}
 	}
-
+	
 	fn run_statement(&mut self, statement: &Statement) -> bool {
 		match statement {
 			CommandStmt(stmt) => { self.run_command(stmt) },
-			AssignmentStmt(stmt) => { self.run_assignment(stmt) }
+			AssignmentStmt(stmt) => { self.run_assignment(stmt) },
+			LabelStmt(stmt) => { self.run_label(stmt) },
+			PercentStmt(stmt) => { self.run_percent(stmt) }
 		}
 	}
 	
-	fn run_command(&self, statement: &CommandStatement) -> bool {
+	fn run_command(&mut self, statement: &CommandStatement) -> bool {
 		match statement.command {
+			CommandType::Alias => {
+				self.run_alias(&statement.argument);
+			},
+			CommandType::Assert => {
+				if let CommandArgument::Expr(expression) = &statement.argument {
+					self.run_assert(expression);
+				}
+			},
+			CommandType::AutoRecalc => {
+				self.run_autorecalc(&statement.argument);
+			},
+			CommandType::Bin => {
+				self.format.base = Base::Binary;
+			},
+			CommandType::Bits => {
+				if let CommandArgument::Expr(expression) = &statement.argument {
+					self.run_bits(expression);
+				}
+			},
+			CommandType::Clear => {
+				self.context.variables.clear();
+				self.definitions.clear();
+			},
+			CommandType::Convert => {
+				if let CommandArgument::Conversion(expression, from, to) = &statement.argument {
+					self.run_convert(expression, from, to);
+				}
+			},
+			CommandType::Count => {
+				self.print(&self.context.variables.len().to_string());
+			},
+			CommandType::Dec => {
+				self.format.base = Base::Decimal;
+			},
+			CommandType::DecimalPoint => {
+				self.run_decimal_point(&statement.argument);
+			},
+			CommandType::Degrees => {
+				self.context.angle_mode = AngleMode::Degrees;
+			},
+			CommandType::Del => {
+				self.run_del(&statement.argument);
+			},
+			CommandType::Dump => {
+				self.run_dump();
+			},
+			CommandType::Errors => {
+				self.run_errors(&statement.argument);
+			},
 			CommandType::Help => {
-				println!("{}", help());
+				self.print(&help());
+			},
+			CommandType::Hex => {
+				self.format.base = Base::Hexadecimal;
+			},
+			CommandType::History => {
+				for (i, line) in self.history.clone().iter().enumerate() {
+					self.print(&format!("{:>4}  {}", i + 1, line));
+				}
+			},
+			CommandType::Multiline => {
+				self.run_multiline(&statement.argument);
+			},
+			CommandType::NumSteps => {
+				self.run_numsteps(&statement.argument);
+			},
+			CommandType::Peek => {
+				if let CommandArgument::Expr(expression) = &statement.argument {
+					self.show(expression);
+				}
+			},
+			CommandType::Prec => {
+				self.run_prec(&statement.argument);
+			},
+			CommandType::Precision => {
+				self.run_precision(&statement.argument);
+			},
+			CommandType::Radians => {
+				self.context.angle_mode = AngleMode::Radians;
+			},
+			CommandType::Load => {
+				if let CommandArgument::Path(path) = &statement.argument {
+					return self.run_load(path);
+				}
 			},
 			CommandType::Quit => {
 				return false;
+			},
+			CommandType::Recompute => {
+				return self.run_recompute(&statement.argument);
+			},
+			CommandType::RoundMode => {
+				self.run_roundmode(&statement.argument);
+			},
+			CommandType::Save => {
+				if let CommandArgument::Path(path) = &statement.argument {
+					self.run_save(path);
+				}
+			},
+			CommandType::Search => {
+				if let CommandArgument::Text(text) = &statement.argument {
+					self.run_search(text);
+				}
+			},
+			CommandType::Seed => {
+				self.run_seed(&statement.argument);
+			},
+			CommandType::SigFigs => {
+				self.run_sigfigs(&statement.argument);
+			},
+			CommandType::Stat => {
+				self.run_stat(&statement.argument);
+			},
+			CommandType::Step => {
+				if let CommandArgument::Expr(expression) = &statement.argument {
+					self.run_step(expression);
+				}
+			},
+			CommandType::Store => {
+				self.run_store(&statement.argument);
+			},
+			CommandType::Vars => {
+				self.run_vars(&statement.argument);
+			},
+			CommandType::Why => {
+				self.run_why();
 			}
 		}
 		
 		true
 	}
 	
+	// List the input lines that contain the text, numbered from the first.
+	fn run_search(&mut self, text: &str) {
+		if text.is_empty() {
+			self.fail("search: expected text to search for, e.g. 'search sqrt'.");
+			return;
+		}
+		
+		for (i, line) in self.history.clone().iter().enumerate() {
+			if line.contains(text) {
+				self.print(&format!("{:>4}  {}", i + 1, line));
+			}
+		}
+	}
+	
+	// List variables sorted by name, with the values aligned. "vars def"
+	// also shows the expression each variable was last assigned from.
+	fn run_vars(&mut self, argument: &CommandArgument) {
+		let show_definitions = match argument {
+			CommandArgument::Words(words) if words.is_empty() => false,
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "def" => true,
+			_ => {
+				self.fail("vars: the only supported option is 'def'.");
+				return;
+			}
+		};
+		
+		let mut names: Vec<String> = self.context.variables.keys().cloned().collect();
+		names.sort();
+		let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+		
+		for name in names {
+			let value = self.format.format(self.context.variables[&name]);
+			
+			match self.definitions.get(&name) {
+				Some(definition) if show_definitions => {
+					let line = format!("{name:width$} = {value}  (from: {definition})");
+					self.print(&line);
+				},
+				_ => {
+					self.print(&format!("{name:width$} = {value}"));
+				}
+			}
+		}
+	}
+	
+	// Give a function another name, as in "alias r=sqrt", or list the
+	// aliases. An alias can be redefined, but it cannot take a name that
+	// is in use otherwise.
+	fn run_alias(&mut self, argument: &CommandArgument) {
+		let CommandArgument::Words(words) = argument else {
+			return;
+		};
+		
+		if words.is_empty() {
+			for (alias, func) in self.aliases.clone() {
+				self.print(&format!("alias {alias}={func}"));
+			}
+			
+			return;
+		}
+		
+		let definition = words.concat();
+		
+		let Some((alias, target)) = definition.split_once('=') else {
+			self.fail("alias: expected a name and a function, e.g. 'alias r=sqrt'.");
+			return;
+		};
+		
+		let mut lexer = Lexer::with_names(target, Vec::new(), self.aliases.clone());
+		
+		let func = match (lexer.get_next(), lexer.get_next()) {
+			(Ok(Token::Function(func)), Ok(Token::Eol)) => func,
+			_ => {
+				self.fail(&format!("alias: {target} is not a function."));
+				return;
+			}
+		};
+		
+		let mut lexer = Lexer::with_names(alias, self.label_names(), self.aliases.clone());
+		
+		match (lexer.get_next(), lexer.get_next()) {
+			(Ok(Token::Variable(name)), Ok(Token::Eol)) if self.context.variables.contains_key(&name) => {
+				self.fail(&format!("alias: {alias} is already a variable."));
+				return;
+			},
+			(Ok(Token::Variable(name)), Ok(Token::Eol)) if name != ANS => {},
+			(Ok(Token::Function(_)), Ok(Token::Eol)) if self.aliases.iter().any(|(a, _)| a == alias) => {},
+			(Ok(Token::Command(_) | Token::Constant(_) | Token::Function(_) | Token::Label(_) | Token::Variable(_)), Ok(Token::Eol)) => {
+				self.fail(&format!("alias: {alias} is already in use."));
+				return;
+			},
+			_ => {
+				self.fail(&format!("alias: '{alias}' is not a valid name."));
+				return;
+			}
+		}
+		
+		self.aliases.retain(|(a, _)| a != alias);
+		self.aliases.push((String::from(alias), func));
+		self.aliases.sort_by(|(a, _), (b, _)| a.cmp(b));
+	}
+	
+	// Check that an expression is true, i.e., nonzero, as in "assert x < 1".
+	// Nothing is printed unless it isn't.
+	fn run_assert(&mut self, expression: &Expression) {
+		match expression.evaluate(&mut self.context) {
+			Ok(result) if result != 0.0 => {},
+			Ok(_) => {
+				self.failed_assertions += 1;
+				self.fail(&format!("assertion failed: {expression}"));
+			},
+			Err(e) => {
+				self.failed_assertions += 1;
+				self.fail(&e.description);
+			}
+		}
+	}
+	
+	// Turn automatic recalculation on or off, or show the current setting.
+	fn run_autorecalc(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Words(words) if words.is_empty() => {
+				let state = if self.autorecalc { "on" } else { "off" };
+				self.print(&format!("autorecalc is {state}"));
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "on" => {
+				self.autorecalc = true;
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "off" => {
+				self.autorecalc = false;
+			},
+			_ => {
+				self.fail("autorecalc: expected 'on' or 'off'.");
+			}
+		}
+	}
+	
+	// Remove a single variable, unlike clear which removes them all.
+	fn run_del(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Words(words) if words.len() == 1 => {
+				if self.context.variables.remove(&words[0]).is_some() {
+					self.definitions.remove(&words[0]);
+				} else {
+					self.fail(&format!("del: {} is not a variable.", words[0]));
+				}
+			},
+			_ => {
+				self.fail("del: expected a variable name, e.g. 'del x'.");
+			}
+		}
+	}
+	
+	// Choose how errors are displayed, or show the current setting.
+	fn run_errors(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Words(words) if words.is_empty() => {
+				let state = if self.verbose_errors { "verbose" } else { "terse" };
+				self.print(&format!("errors is {state}"));
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "terse" => {
+				self.verbose_errors = false;
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "verbose" => {
+				self.verbose_errors = true;
+			},
+			_ => {
+				self.fail("errors: expected 'terse' or 'verbose'.");
+			}
+		}
+	}
+	
+	// Turn significant figures on or off, or show the current setting.
+	fn run_sigfigs(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Words(words) if words.is_empty() => {
+				let state = if self.sigfigs { "on" } else { "off" };
+				self.print(&format!("sigfigs is {state}"));
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "on" => {
+				self.sigfigs = true;
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "off" => {
+				self.sigfigs = false;
+			},
+			_ => {
+				self.fail("sigfigs: expected 'on' or 'off'.");
+			}
+		}
+	}
+	
+	// Set how round() picks an integer, or show the current setting.
+	fn run_roundmode(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Words(words) if words.is_empty() => {
+				let mode = self.context.round_mode;
+				self.print(&format!("roundmode is {mode}"));
+			},
+			CommandArgument::Words(words) if words.len() == 1 && let Some((_, mode)) = ROUND_MODES.iter().find(|(name, _)| *name == words[0]) => {
+				self.context.round_mode = *mode;
+			},
+			_ => {
+				let names: Vec<&str> = ROUND_MODES.iter().map(|(name, _)| *name).collect();
+				self.fail(&format!("roundmode: expected one of {}.", names.join(", ")));
+			}
+		}
+	}
+	
+	// Run line n of the history again, showing it first.
+	fn run_recall(&mut self, n: usize) -> bool {
+		match self.history.get(n - 1).cloned() {
+			Some(_) if self.recall_depth >= MAX_RECALL_DEPTH => {
+				self.fail(&format!("!{n}: too many recalls within recalls; does line {n} recall itself?"));
+				true
+			},
+			Some(line) => {
+				self.print(&line);
+				self.recall_depth += 1;
+				let keep_going = self.run_input(&line);
+				self.recall_depth -= 1;
+				keep_going
+			},
+			None => {
+				self.fail(&format!("!{n}: there is no line {n} in the history."));
+				true
+			}
+		}
+	}
+	
+	// Restart the random choices from a given seed, so that they come out
+	// the same every time.
+	fn run_seed(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Words(words) if words.len() == 1 && let Ok(seed) = words[0].parse::<u64>() => {
+				self.context.random = Random::new(seed);
+			},
+			_ => {
+				self.fail("seed: expected a nonnegative integer, e.g. 'seed 42'.");
+			}
+		}
+	}
+	
+	// Set the number of subintervals for integ, or show the current setting.
+	fn run_numsteps(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Words(words) if words.is_empty() => {
+				let steps = self.context.steps;
+				self.print(&format!("numsteps is {steps}"));
+			},
+			CommandArgument::Words(words) if words.len() == 1 && let Ok(steps) = words[0].parse::<usize>() && (1..=MAX_STEPS).contains(&steps) => {
+				self.context.steps = steps;
+			},
+			_ => {
+				self.fail(&format!("numsteps: expected a number of steps from 1 to {MAX_STEPS}."));
+			}
+		}
+	}
+	
+	// Turn multiline mode on or off, or show the current setting. Note that
+	// in multiline mode "multiline off" is itself collected like any other
+	// input, and takes effect after the following blank line.
+	fn run_multiline(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Words(words) if words.is_empty() => {
+				let state = if self.multiline { "on" } else { "off" };
+				self.print(&format!("multiline is {state}"));
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "on" => {
+				self.multiline = true;
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "off" => {
+				self.multiline = false;
+			},
+			_ => {
+				self.fail("multiline: expected 'on' or 'off'.");
+			}
+		}
+	}
+	
+	// Show how tightly a binary operator binds, as given by BINARY_OPERATORS.
+	fn run_prec(&mut self, argument: &CommandArgument) {
+		let operator = match argument {
+			CommandArgument::Words(words) if words.len() == 1 => {
+				let mut lexer = Lexer::new(&words[0]);
+				
+				match (lexer.get_next(), lexer.get_next()) {
+					(Ok(Token::Operator(op)), Ok(Token::Eol)) => binary_operator(op).map(|info| (op, info)),
+					_ => None
+				}
+			},
+			_ => None
+		};
+		
+		match operator {
+			Some((op, (precedence, associativity))) => {
+				let associativity = match associativity {
+					Associativity::Left => "left",
+					Associativity::Right => "right"
+				};
+				self.print(&format!("{op}: precedence {precedence}, {associativity}-associative"));
+			},
+			None => {
+				self.fail("prec: expected a binary operator, e.g. 'prec ^'.");
+			}
+		}
+	}
+	
+	// Explain what kind of operation produced the latest result.
+	fn run_why(&mut self) {
+		match (self.context.ans, &self.last_expression) {
+			(Some(result), Some(expression)) => {
+				let line = format!("{} = {} ({})", self.format.format(result), expression, describe(expression));
+				self.print(&line);
+			},
+			_ => {
+				self.fail("why: nothing has been computed yet.");
+			}
+		}
+	}
+	
+	// Assign a variable again from the expression it was last assigned
+	// from, using the current values of the variables it refers to. This
+	// only recomputes the one variable, not the ones that depend on it.
+	fn run_recompute(&mut self, argument: &CommandArgument) -> bool {
+		let definition = match argument {
+			CommandArgument::Words(words) if words.len() == 1 => {
+				match self.definitions.get(&words[0]) {
+					Some(definition) => AssignmentStatement::new(VariableExpression::new(words[0].clone()), definition.clone()),
+					None => {
+						self.fail(&format!("recompute: {} is not a variable.", words[0]));
+						return true;
+					}
+				}
+			},
+			_ => {
+				self.fail("recompute: expected a variable name, e.g. 'recompute x'.");
+				return true;
+			}
+		};
+		
+		self.run_assignment(&definition)
+	}
+	
+	// Add a value to the dataset, clear it, or show its statistics. The
+	// standard deviation is that of a sample, and zero for a single value.
+	fn run_stat(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Expr(expression) => {
+				match expression.evaluate_finite(&mut self.context) {
+					Ok(value) => {
+						self.dataset.push(value);
+						let line = format!("added {}, count {}", self.format.format(value), self.dataset.len());
+						self.print(&line);
+					},
+					Err(e) => {
+						self.fail(&e.description);
+					}
+				}
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "clear" => {
+				self.dataset.clear();
+			},
+			CommandArgument::Words(words) if words.is_empty() => {
+				if self.dataset.is_empty() {
+					self.fail("stat: no values have been added.");
+					return;
+				}
+				
+				let count = self.dataset.len() as f64;
+				let mean = self.dataset.iter().sum::<f64>() / count;
+				let min = self.dataset.iter().cloned().fold(f64::INFINITY, f64::min);
+				let max = self.dataset.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+				let squares: f64 = self.dataset.iter().map(|x| (x - mean) * (x - mean)).sum();
+				let stddev = if self.dataset.len() > 1 { (squares / (count - 1.0)).sqrt() } else { 0.0 };
+				
+				self.print(&format!("count  {}", self.dataset.len()));
+				self.print(&format!("mean   {}", self.format.format(mean)));
+				self.print(&format!("min    {}", self.format.format(min)));
+				self.print(&format!("max    {}", self.format.format(max)));
+				self.print(&format!("stddev {}", self.format.format(stddev)));
+			},
+			_ => {
+				self.fail("stat: expected 'add <expr>', 'clear' or nothing.");
+			}
+		}
+	}
+	
+	// Copy the latest result into a variable, as if it had been assigned
+	// from the expression that produced it.
+	fn run_store(&mut self, argument: &CommandArgument) {
+		let var = match argument {
+			CommandArgument::Words(words) if words.len() == 1 => {
+				let mut lexer = Lexer::with_names(&words[0], self.label_names(), self.aliases.clone());
+				
+				match (lexer.get_next(), lexer.get_next()) {
+					(Ok(Token::Variable(var)), Ok(Token::Eol)) if var != ANS => var,
+					_ => {
+						self.fail(&format!("store: {} cannot be used as a variable.", words[0]));
+						return;
+					}
+				}
+			},
+			_ => {
+				self.fail("store: expected a variable name, e.g. 'store x'.");
+				return;
+			}
+		};
+		
+		match (self.context.ans, &self.last_expression) {
+			(Some(result), Some(expression)) => {
+				self.context.variables.insert(var.clone(), result);
+				self.definitions.insert(var.clone(), expression.clone());
+				let result = self.format.format(result);
+				self.print(&format!("{var} = {result}"));
+			},
+			_ => {
+				self.fail("store: nothing has been computed yet.");
+			}
+		}
+	}
+	
+	fn run_dump(&mut self) {
+		for line in self.dump() {
+			self.print(&line);
+		}
+	}
+	
+	// Write what dump prints to a file, to be restored with load.
+	fn run_save(&mut self, path: &str) {
+		let text = self.dump().join("\n") + "\n";
+		
+		if let Err(e) = fs::write(path, text) {
+			self.fail(&format!("save: cannot write {path}: {e}"));
+		}
+	}
+	
+	// Run the lines of a file, as with --load. Return false if it asked to
+	// exit.
+	fn run_load(&mut self, path: &str) -> bool {
+		match self.load(path) {
+			Ok(keep_going) => keep_going,
+			Err(e) => {
+				self.fail(&format!("load: {}", e.description));
+				true
+			}
+		}
+	}
+	
+	// The settings, variables and labels as input that recreates them when
+	// run, e.g., as a script with --load. Values are written in full rather
+	// than as the expressions they came from, as those may refer to
+	// variables that have been changed since, but the expressions are
+	// included as comments.
+	fn dump(&self) -> Vec<String> {
+		let mut lines = Vec::<String>::new();
+		
+		lines.push(match self.format.precision {
+			Precision::Auto => String::from("precision auto"),
+			Precision::Fixed(decimals) => format!("precision {decimals}"),
+			Precision::Scientific => String::from("precision sci")
+		});
+		lines.push(String::from(if self.format.always_decimal_point { "decimalpoint always" } else { "decimalpoint auto" }));
+		lines.push(String::from(match self.format.base {
+			Base::Decimal => "dec",
+			Base::Hexadecimal => "hex",
+			Base::Binary => "bin"
+		}));
+		lines.push(String::from(if self.autorecalc { "autorecalc on" } else { "autorecalc off" }));
+		lines.push(String::from(if self.sigfigs { "sigfigs on" } else { "sigfigs off" }));
+		lines.push(format!("numsteps {}", self.context.steps));
+		lines.push(String::from(match self.context.angle_mode {
+			AngleMode::Radians => "radians",
+			AngleMode::Degrees => "degrees"
+		}));
+		lines.push(format!("roundmode {}", self.context.round_mode));
+		lines.push(String::from(if self.verbose_errors { "errors verbose" } else { "errors terse" }));
+		
+		for (alias, func) in &self.aliases {
+			lines.push(format!("alias {alias}={func}"));
+		}
+		
+		let mut names: Vec<&String> = self.context.variables.keys().collect();
+		names.sort();
+		
+		for name in names {
+			let value = self.context.variables[name];
+			
+			match self.definitions.get(name) {
+				Some(definition) => { lines.push(format!("{name} = {value}  # from: {definition}")); },
+				None => { lines.push(format!("{name} = {value}")); }
+			}
+		}
+		
+		let mut labels: Vec<&String> = self.context.labels.keys().collect();
+		labels.sort();
+		
+		for label in labels {
+			lines.push(format!("{label}: {}", self.context.labels[label]));
+		}
+		
+		// Last, as it changes how the lines following it are run.
+		if self.multiline {
+			lines.push(String::from("multiline on"));
+		}
+		
+		lines
+	}
+	
+	// Set the number of decimals displayed, "auto" for the shortest form that
+	// still identifies the value, "sci" for the same in scientific notation,
+	// or show the current setting.
+	fn run_precision(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Words(words) if words.is_empty() => {
+				let current = match self.format.precision {
+					Precision::Auto => String::from("precision is automatic"),
+					Precision::Fixed(decimals) => format!("precision is {decimals} decimals"),
+					Precision::Scientific => String::from("precision is automatic, in scientific notation")
+				};
+				self.print(&current);
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "auto" => {
+				self.format.precision = Precision::Auto;
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "sci" => {
+				self.format.precision = Precision::Scientific;
+			},
+			CommandArgument::Words(words) if words.len() == 1 && let Ok(decimals) = words[0].parse::<usize>() => {
+				self.format.precision = Precision::Fixed(decimals);
+			},
+			_ => {
+				self.fail("precision: expected the number of decimals, 'auto' or 'sci'.");
+			}
+		}
+	}
+	
+	// Evaluate an expression one operation at a time, innermost first,
+	// showing each with the values of its operands, e.g. "3 + 4 = 7" and
+	// then "2 * 7 = 14" for 2*(3+4). Like peek, this doesn't change the
+	// latest result.
+	fn run_step(&mut self, expression: &Expression) {
+		let mut shown = false;
+		
+		match self.step(expression, &mut shown) {
+			// With no operation to show, as in "step x", show the value alone.
+			Ok(result) if !shown => {
+				let text = expression.to_string();
+				let value = self.format.format(result);
+				self.print(&if text == value { value } else { format!("{text} = {value}") });
+			},
+			Ok(_) => {},
+			Err(e) => {
+				self.fail(&e.description);
+			}
+		}
+	}
+				
+	// Set shown once a step has been printed.
+	fn step(&mut self, expression: &Expression, shown: &mut bool) -> Result<f64, Error> {
+		let (text, result) = match expression {
+			Expression::ParenExpr(e) => { return self.step(&e.expr, shown); },
+			Expression::UnaryExpr(e) => {
+				let value = self.step(&e.expr, shown)?;
+				let result = if e.op == OperatorType::Plus { value } else { -value };
+				
+				// The sign of a number, as in "-3", is part of how it's
+				// written rather than a step.
+				if e.op == OperatorType::Plus || matches!(e.expr, Expression::LiteralExpr(_)) {
+					return Ok(result);
+				}
+				
+				(format!("-{}", self.format.format(value)), result)
+			},
+			Expression::BinaryExpr(e) => {
+				let left = self.step(&e.left, shown)?;
+				let right = self.step(&e.right, shown)?;
+				(format!("{} {} {}", self.format.format(left), e.op, self.format.format(right)), e.apply(left, right)?)
+			},
+			// The arguments of if are evaluated only when selected, so it
+			// is shown as a whole.
+			Expression::FunctionExpr(e) if e.func != FunctionType::If => {
+				let mut args = Vec::new();
+				
+				for arg in &e.args {
+					args.push(self.step(arg, shown)?);
+				}
+				
+				let texts: Vec<String> = args.iter().map(|value| self.format.format(*value)).collect();
+				let literals = args.into_iter().map(|value| Expression::LiteralExpr(Box::new(LiteralExpression::new(value)))).collect();
+				(format!("{}({})", e.func, texts.join(", ")), FunctionExpression::new(e.func, literals).evaluate(&mut self.context)?)
+			},
+			Expression::LiteralExpr(_) | Expression::VariableExpr(_) | Expression::ConstantExpr(_) | Expression::LabelExpr(_) => {
+				return expression.evaluate(&mut self.context);
+			},
+			_ => (expression.to_string(), expression.evaluate(&mut self.context)?)
+		};
+		
+		let line = format!("{text} = {}", self.format.format(result));
+		self.print(&line);
+		*shown = true;
+		Ok(result)
+	}
+	
+	// Show the IEEE-754 representation of a value, split into its fields.
+	fn run_bits(&mut self, expression: &Expression) {
+		match expression.evaluate(&mut self.context) {
+			Ok(result) => {
+				let bits = result.to_bits();
+				let exponent = (bits >> 52) & 0x7ff;
+				self.print(&format!("hex      0x{:016X}", bits));
+				self.print(&format!("sign     {}", bits >> 63));
+				self.print(&format!("exponent {:011b} ({})", exponent, exponent));
+				self.print(&format!("mantissa {:052b}", bits & ((1 << 52) - 1)));
+			},
+			Err(e) => {
+				self.fail(&e.description);
+			}
+		}
+	}
+	
+	// Convert a value between units. Like peek, this doesn't change the
+	// latest result.
+	fn run_convert(&mut self, expression: &Expression, from: &str, to: &str) {
+		let result = expression.evaluate_finite(&mut self.context)
+			.and_then(|value| convert(value, from, to));
+		
+		match result {
+			Ok(result) => {
+				let text = format!("{} {}", self.format.format(result), to);
+				self.print(&text);
+			},
+			Err(e) => {
+				self.fail(&e.description);
+			}
+		}
+	}
+	
+	// Choose whether integer-valued results always show a decimal point,
+	// or show the current setting.
+	fn run_decimal_point(&mut self, argument: &CommandArgument) {
+		match argument {
+			CommandArgument::Words(words) if words.is_empty() => {
+				if self.format.always_decimal_point {
+					self.print("decimal point is always shown");
+				} else {
+					self.print("decimal point is shown when needed");
+				}
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "always" => {
+				self.format.always_decimal_point = true;
+			},
+			CommandArgument::Words(words) if words.len() == 1 && words[0] == "auto" => {
+				self.format.always_decimal_point = false;
+			},
+			_ => {
+				self.fail("decimalpoint: expected 'always' or 'auto'.");
+			}
+		}
+	}
+	
 	fn run_assignment(&mut self, assignment: &AssignmentStatement) -> bool {
-		match assignment.expression.evaluate(&self.variables) {
+		let var = &assignment.variable.var;
+		
+		if self.autorecalc && let Some(through) = assignment.expression.variables().iter().find(|v| self.depends_on(v, var)) {
+			self.fail(&format!("{var} cannot depend on {through}, which depends on {var}."));
+			return true;
+		}
+		
+		match self.assign(assignment) {
 			Ok(result) => {
-				let var = assignment.variable.var;
-				self.variables.insert(var, result);
-				println!("{var} = {result}")
+				self.context.ans = Some(result);
+				self.last_expression = Some(assignment.expression.clone());
+				let result = self.display(&assignment.expression, result);
+				self.print(&format!("{var} = {result}"));
+				
+				if self.autorecalc {
+					self.recalculate_dependents(var);
+				}
 			}
 			Err(e) => {
-				println!("{}", e.description);
+				self.fail(&e.description);
 			}
 		}
 		
 		true
 	}
 	
-	fn run_expression(&self, expression: &Expression) -> bool {
-		match expression.evaluate(&self.variables) {
+	// Whether a variable's definition refers to another variable, directly
+	// or through other variables. A variable that refers to itself, as in
+	// "x = x + 1", refers to its previous value and doesn't count.
+	fn depends_on(&self, var: &str, other: &str) -> bool {
+		let mut visited = HashSet::new();
+		let mut pending = vec![String::from(var)];
+		
+		while let Some(current) = pending.pop() {
+			if let Some(definition) = self.definitions.get(&current) {
+				for used in definition.variables() {
+					if used == other && current != other {
+						return true;
+					} else if used != current && visited.insert(used.clone()) {
+						pending.push(used);
+					}
+				}
+			}
+		}
+		
+		false
+	}
+	
+	// Recompute the variables that depend on the given one, each after the
+	// ones it depends on.
+	fn recalculate_dependents(&mut self, var: &str) {
+		let mut order = Vec::new();
+		
+		if let Err(e) = self.order_dependents(var, &mut Vec::new(), &mut order) {
+			self.fail(&e.description);
+			return;
+		}
+		
+		for dependent in order.iter().rev().skip(1) {
+			match self.definitions[dependent].evaluate_finite(&mut self.context) {
+				Ok(result) => {
+					self.context.variables.insert(dependent.clone(), result);
+					let result = self.format.format(result);
+					self.print(&format!("{dependent} = {result}"));
+				},
+				Err(e) => {
+					self.fail(&format!("{dependent}: {}", e.description));
+					return;
+				}
+			}
+		}
+	}
+	
+	// Depth-first search for the variables that depend on var, which are
+	// added to order after everything that depends on them, followed by var
+	// itself. Variables that depend on each other were assigned while
+	// autorecalc was off, and are an error.
+	fn order_dependents(&self, var: &str, path: &mut Vec<String>, order: &mut Vec<String>) -> Result<(), Error> {
+		if let Some(start) = path.iter().position(|v| v == var) {
+			return Err(Error::new(&format!("cannot recalculate, as {} depend on each other.", path[start..].join(", "))));
+		} else if order.iter().any(|v| v == var) {
+			return Ok(());
+		}
+		
+		let mut dependents: Vec<&String> = self.definitions.iter()
+			.filter(|(name, definition)| *name != var && definition.variables().contains(var))
+			.map(|(name, _)| name)
+			.collect();
+		dependents.sort();
+		
+		path.push(String::from(var));
+		
+		for dependent in dependents {
+			self.order_dependents(dependent, path, order)?;
+		}
+		
+		path.pop();
+		order.push(String::from(var));
+		Ok(())
+	}
+	
+	fn assign(&mut self, assignment: &AssignmentStatement) -> Result<f64, Error> {
+		let var = &assignment.variable.var;
+		
+		if var == ANS {
+			return Err(Error::new(&format!("cannot assign to {ANS}, which is the latest result.")));
+		}
+		
+		let result = assignment.expression.evaluate_finite(&mut self.context)?;
+		self.context.variables.insert(var.clone(), result);
+		self.definitions.insert(var.clone(), assignment.expression.clone());
+		Ok(result)
+	}
+	
+	// Unlike variables, labels cannot be reassigned.
+	fn run_label(&mut self, statement: &LabelStatement) -> bool {
+		if self.context.labels.contains_key(&statement.label) {
+			self.fail(&format!("label {} is already defined.", statement.label));
+			return true;
+		} else if self.context.variables.contains_key(&statement.label) {
+			self.fail(&format!("{} is already a variable.", statement.label));
+			return true;
+		} else if statement.label == ANS {
+			self.fail(&format!("{ANS} is the latest result and cannot be a label."));
+			return true;
+		}
+		
+		match statement.expression.evaluate_finite(&mut self.context) {
 			Ok(result) => {
-				println!("{result}")
+				self.context.labels.insert(statement.label.clone(), result);
+				let result = self.display(&statement.expression, result);
+				self.print(&format!("{}: {result}", statement.label));
 			}
 			Err(e) => {
-				println!("{}", e.description);
+				self.fail(&e.description);
 			}
 		}
 		
 		true
 	}
+	
+	// Add a percentage of the latest result to it, or subtract it. This is
+	// run as the expression "result + result * percentage / 100", which
+	// becomes the latest expression.
+	fn run_percent(&mut self, statement: &PercentStatement) -> bool {
+		let Some(base) = self.context.ans else {
+			self.fail("nothing has been computed yet to take a percentage of.");
+			return true;
+		};
+		
+		let base = Expression::LiteralExpr(Box::new(LiteralExpression::new(base)));
+		let percentage = Expression::ParenExpr(Box::new(ParenExpression::new(statement.expression.clone())));
+		let part = Expression::BinaryExpr(Box::new(BinaryExpression::new(OperatorType::Times, base.clone(), percentage)));
+		let hundred = Expression::LiteralExpr(Box::new(LiteralExpression::new(100.0)));
+		let part = Expression::BinaryExpr(Box::new(BinaryExpression::new(OperatorType::DividedBy, part, hundred)));
+		let expression = Expression::BinaryExpr(Box::new(BinaryExpression::new(statement.op, base, part)));
+		self.run_expression(&expression)
+	}
+	
+	fn run_expression(&mut self, expression: &Expression) -> bool {
+		if let Some(result) = self.show(expression) {
+			self.context.ans = Some(result);
+			self.last_expression = Some(expression.clone());
+		}
+		
+		true
+	}
+	
+	// Evaluate and print an expression without any bookkeeping, which is
+	// also all that "peek" does. Return the result if there was one.
+	fn show(&mut self, expression: &Expression) -> Option<f64> {
+		match expression.evaluate_finite(&mut self.context) {
+			Ok(result) => {
+				let text = self.display(expression, result);
+				self.print(&text);
+				Some(result)
+			}
+			Err(e) => {
+				self.fail(&e.description);
+				None
+			}
+		}
+	}
+	
+	// Complete the name being typed at the end of the line, as with Tab,
+	// to a command, a function, an alias or a variable. See complete().
+	pub fn complete(&self, line: &str) -> (usize, Vec<String>) {
+		let names: Vec<String> = self.aliases.iter().map(|(alias, _)| alias.clone())
+			.chain(self.context.variables.keys().cloned())
+			.collect();
+		complete(line, &names)
+	}
+	
+	fn label_names(&self) -> Vec<String> {
+		self.context.labels.keys().cloned().collect()
+	}
+	
+	// Format the result of an expression for display. With sigfigs on, a
+	// result computed from number literals is rounded to its significant
+	// figures, and the precision setting doesn't apply.
+	fn display(&mut self, expression: &Expression, result: f64) -> String {
+		if self.sigfigs && let Ok(Significance::Measured { last, .. }) = significance(expression, &mut self.context) {
+			format_measured(result, last)
+		} else if self.format.base != Base::Decimal && self.format.in_base(result).is_none() {
+			let reason = if result.fract() == 0.0 { "too large for 64 bits" } else { "not an integer" };
+			format!("{} ({reason}, so in decimal)", self.format.format(result))
+		} else {
+			self.format.format(result)
+		}
+	}
+	
+	// Print an error, which stops the statements following it on the line.
+	// The error, followed in verbose mode by the input and a caret under
+	// the column where the error was found. Line breaks and tabs are shown
+	// as spaces to keep the caret in place.
+	fn render_error(&self, error: &Error, input: &str) -> String {
+		match error.column {
+			Some(column) if self.verbose_errors => {
+				let line: String = input.chars().map(|c| if c.is_whitespace() { ' ' } else { c }).collect();
+				format!("{}\n{}\n{}^", error.description, line.trim_end(), " ".repeat(column))
+			},
+			_ => error.description.clone()
+		}
+	}
+	
+	fn fail(&mut self, text: &str) {
+		self.print(text);
+		self.failed = true;
+	}
+	
+	fn print(&mut self, text: &str) {
+		writeln!(self.output, "{}", text).expect("Output error!");
+	}
+}
+
+// Describe the outermost operation of an expression.
+fn describe(expression: &Expression) -> String {
+	match expression {
+		Expression::ParenExpr(e) => describe(&e.expr),
+		Expression::UnaryExpr(e) if e.op == OperatorType::Minus => String::from("negation"),
+		Expression::UnaryExpr(e) => describe(&e.expr),
+		Expression::BinaryExpr(e) => String::from(match e.op {
+			OperatorType::Plus => "addition",
+			OperatorType::Minus => "subtraction",
+			OperatorType::Times => "multiplication",
+			OperatorType::DividedBy => "division",
+			OperatorType::FloorDiv => "floor division",
+			OperatorType::Modulo => "remainder",
+			OperatorType::Power => "exponentiation",
+			OperatorType::Less | OperatorType::LessOrEqual | OperatorType::Greater | OperatorType::GreaterOrEqual
+					| OperatorType::Equal | OperatorType::NotEqual => "comparison",
+			_ => "binary operation"
+		}),
+		Expression::FunctionExpr(e) => format!("function {}", e.func),
+		Expression::VariableExpr(e) => format!("value of variable {}", e.var),
+		Expression::ConstantExpr(e) => format!("constant {}", e.constant),
+		Expression::LabelExpr(e) => format!("value of label {}", e.label),
+		Expression::LiteralExpr(_) => String::from("number"),
+		Expression::ReduceExpr(e) => format!("reduce with {}", e.op),
+		Expression::IntegralExpr(_) => String::from("integral"),
+		Expression::EnvExpr(e) => format!("environment variable {}", e.name),
+		Expression::CommandValueExpr(e) => format!("value of command {}", e.command)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn vars_lists_values_sorted_by_name() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("b = 3");
+		runner.run_line("a = 2");
+		assert_eq!(output_of(&mut runner, "vars"), "a = 2\nb = 3\n");
+	}
+	
+	#[test]
+	fn vars_def_lists_values_and_definitions() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("a = 2");
+		runner.run_line("b = a*3 + 1");
+		assert_eq!(output_of(&mut runner, "vars def"), "a = 2  (from: 2)\nb = 7  (from: a * 3 + 1)\n");
+	}
+	
+	#[test]
+	fn vars_aligns_values() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("x = 1");
+		runner.run_line("velocity = 20");
+		runner.run_line("m = x + 2");
+		assert_eq!(output_of(&mut runner, "vars"), "m        = 3\nvelocity = 20\nx        = 1\n");
+		assert_eq!(output_of(&mut runner, "vars def"),
+			"m        = 3  (from: x + 2)\nvelocity = 20  (from: 20)\nx        = 1  (from: 1)\n");
+	}
+	
+	#[test]
+	fn vars_rejects_unknown_option() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("a = 2");
+		assert_eq!(output_of(&mut runner, "vars foo"), "vars: the only supported option is 'def'.\n");
+	}
+	
+	#[test]
+	fn multi_char_variables_are_assigned_and_used() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "vel = 3"), "vel = 3\n");
+		assert_eq!(output_of(&mut runner, "x_1 = 2vel"), "x_1 = 6\n");
+		assert_eq!(output_of(&mut runner, "vel x_1"), "18\n");
+		assert_eq!(output_of(&mut runner, "vars"), "vel = 3\nx_1 = 6\n");
+	}
+	
+	#[test]
+	fn implicit_multiplication_binds_looser_than_power() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("x = 3");
+		assert_eq!(output_of(&mut runner, "2x^2"), "18\n");
+		assert_eq!(output_of(&mut runner, "(2x)^2"), "36\n");
+		assert_eq!(output_of(&mut runner, "2 x^2"), "18\n");
+	}
+	
+	#[test]
+	fn presets_are_assigned_silently() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.preset("a=2").expect("preset failed!");
+		runner.preset("b = a + 1").expect("preset failed!");
+		assert!(runner.output.is_empty());
+		assert_eq!(output_of(&mut runner, "a*b*10"), "60\n");
+	}
+	
+	#[test]
+	fn preset_must_be_a_valid_assignment() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert!(runner.preset("2+2").is_err());
+		assert!(runner.preset("a=").is_err());
+		assert!(runner.preset("a=1/0").is_err());
+	}
+	
+	#[test]
+	fn script_skips_comments_and_blank_lines() {
+		let mut runner = Runner::with_output(Vec::new());
+		let script = "# setup\n\na = 2\n   \n  # derived values\nb = a + 1\n\n";
+		assert!(runner.run_script(script));
+		assert_eq!(String::from_utf8(runner.output.clone()).unwrap(), "a = 2\nb = 3\n");
+	}
+	
+	#[test]
+	fn script_stops_at_quit() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert!(!runner.run_script("a = 2\nquit\nb = 3\n"));
+		assert_eq!(String::from_utf8(runner.output.clone()).unwrap(), "a = 2\n");
+	}
+	
+	#[test]
+	fn script_is_loaded_from_file() {
+		let path = std::env::temp_dir().join(format!("clicalc-load-test-{}.txt", std::process::id()));
+		fs::write(&path, "# radius\nr = 2\n\n2r\n").expect("cannot write test script!");
+		let mut runner = Runner::with_output(Vec::new());
+		let result = runner.load(path.to_str().unwrap());
+		fs::remove_file(&path).ok();
+		assert!(result.expect("load failed!"));
+		assert_eq!(String::from_utf8(runner.output.clone()).unwrap(), "r = 2\n4\n");
+	}
+	
+	#[test]
+	fn session_is_saved_and_loaded() {
+		let path = std::env::temp_dir().join(format!("clicalc-session-test-{}.txt", std::process::id()));
+		let path = path.to_str().unwrap();
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("precision 2");
+		runner.run_line("a = 1/3");
+		runner.run_line("b = -a*3e10");
+		runner.run_line("total: a + b");
+		assert_eq!(output_of(&mut runner, &format!("save \"{path}\"")), "");
+		
+		let mut restored = Runner::with_output(Vec::new());
+		let output = output_of(&mut restored, &format!("load \"{path}\""));
+		fs::remove_file(path).ok();
+		assert_eq!(output, "a = 0.33\nb = -10000000000.00\ntotal: -9999999999.67\n");
+		assert_eq!(restored.context.variables, runner.context.variables);
+		assert_eq!(restored.context.labels, runner.context.labels);
+		assert!(restored.format.precision == Precision::Fixed(2));
+	}
+	
+	#[test]
+	fn save_and_load_report_file_errors() {
+		let mut runner = Runner::with_output(Vec::new());
+		let output = output_of(&mut runner, "load \"/nonexistent/clicalc-session\"");
+		assert!(output.starts_with("load: cannot read /nonexistent/clicalc-session: "), "{}", output);
+		let output = output_of(&mut runner, "save \"/nonexistent/clicalc-session\"");
+		assert!(output.starts_with("save: cannot write /nonexistent/clicalc-session: "), "{}", output);
+		assert_eq!(output_of(&mut runner, "save session.txt"), "Parse error: save: expected a file name in quotes, e.g. 'save \"session.txt\"'.\n");
+		assert_eq!(output_of(&mut runner, "load"), "Parse error: load: expected a file name in quotes, e.g. 'load \"session.txt\"'.\n");
+	}
+	
+	#[test]
+	fn missing_script_is_an_error() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert!(runner.load("/nonexistent/clicalc-script").is_err());
+	}
+	
+	#[test]
+	fn unused_variables_are_reported() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.preset("a = 2").expect("preset failed!");
+		runner.run_line("b = 3");
+		runner.run_line("c = a * 2");
+		assert_eq!(runner.unused_variables(), vec!["b", "c"]);
+		runner.run_line("c + 1");
+		assert_eq!(runner.unused_variables(), vec!["b"]);
+	}
+	
+	#[test]
+	fn precision_rounds_display_but_not_stored_values() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("precision 3");
+		assert_eq!(output_of(&mut runner, "a = 1/3"), "a = 0.333\n");
+		assert_eq!(output_of(&mut runner, "a*3"), "1.000\n");
+		assert_eq!(runner.context.variables["a"], 1.0 / 3.0);
+		assert_eq!(output_of(&mut runner, "precision"), "precision is 3 decimals\n");
+	}
+	
+	#[test]
+	fn precision_applies_to_assignment_echo() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("precision 3");
+		assert_eq!(output_of(&mut runner, "r = sqrt(2)"), "r = 1.414\n");
+		runner.run_line("precision auto");
+		assert_eq!(output_of(&mut runner, "r*r"), "2.0000000000000004\n");
+		assert_eq!(runner.context.variables["r"], 2.0_f64.sqrt());
+	}
+	
+	#[test]
+	fn precision_auto_restores_shortest_display() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("precision 2");
+		assert_eq!(output_of(&mut runner, "0.1"), "0.10\n");
+		runner.run_line("precision auto");
+		assert_eq!(output_of(&mut runner, "0.1"), "0.1\n");
+		assert_eq!(output_of(&mut runner, "1/3"), "0.3333333333333333\n");
+		assert_eq!(output_of(&mut runner, "precision"), "precision is automatic\n");
+	}
+	
+	#[test]
+	fn precision_sci_displays_scientific_notation() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("precision sci");
+		assert_eq!(output_of(&mut runner, "a = -123000"), "a = -1.23e5\n");
+		assert_eq!(output_of(&mut runner, "precision"), "precision is automatic, in scientific notation\n");
+	}
+	
+	#[test]
+	fn precision_requires_a_number() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "precision x"), "precision: expected the number of decimals, 'auto' or 'sci'.\n");
+		assert_eq!(output_of(&mut runner, "precision"), "precision is automatic\n");
+	}
+	
+	#[test]
+	fn prec_shows_operator_precedence() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "prec +"), "+: precedence 1, left-associative\n");
+		assert_eq!(output_of(&mut runner, "prec *"), "*: precedence 2, left-associative\n");
+		assert_eq!(output_of(&mut runner, "prec ^"), "^: precedence 3, left-associative\n");
+		assert_eq!(output_of(&mut runner, "prec //"), "//: precedence 2, left-associative\n");
+	}
+	
+	#[test]
+	fn prec_requires_a_binary_operator() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "prec ("), "prec: expected a binary operator, e.g. 'prec ^'.\n");
+		assert_eq!(output_of(&mut runner, "prec"), "prec: expected a binary operator, e.g. 'prec ^'.\n");
+		assert_eq!(output_of(&mut runner, "prec + *"), "prec: expected a binary operator, e.g. 'prec ^'.\n");
+	}
+	
+	#[test]
+	fn decimalpoint_always_shows_point_in_integers() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "2+2"), "4\n");
+		runner.run_line("decimalpoint always");
+		assert_eq!(output_of(&mut runner, "2+2"), "4.0\n");
+		assert_eq!(output_of(&mut runner, "decimalpoint"), "decimal point is always shown\n");
+		runner.run_line("decimalpoint auto");
+		assert_eq!(output_of(&mut runner, "2+2"), "4\n");
+		assert_eq!(output_of(&mut runner, "decimalpoint never"), "decimalpoint: expected 'always' or 'auto'.\n");
+	}
+	
+	#[test]
+	fn peek_does_not_update_last_result() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "2+2"), "4\n");
+		assert_eq!(output_of(&mut runner, "peek 5"), "5\n");
+		assert_eq!(runner.context.ans, Some(4.0));
+		assert_eq!(output_of(&mut runner, "a = 3"), "a = 3\n");
+		assert_eq!(output_of(&mut runner, "peek a^2"), "9\n");
+		assert_eq!(runner.context.ans, Some(3.0));
+		assert_eq!(output_of(&mut runner, "peek"), "Parse error: unexpected end of input.\n");
+	}
+	
+	#[test]
+	fn blank_input_does_nothing() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, ""), "");
+		assert_eq!(output_of(&mut runner, "  \t\n"), "");
+		assert_eq!(output_of(&mut runner, "# just a comment"), "");
+		assert_eq!(output_of(&mut runner, "1 + 1; ; 2 + 2;"), "2\n4\n");
+	}
+	
+	#[test]
+	fn multiline_input_runs_at_blank_line() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("multiline on");
+		assert_eq!(output_of(&mut runner, "2 +\n"), "");
+		assert_eq!(output_of(&mut runner, "3 # comment\n"), "");
+		assert_eq!(output_of(&mut runner, "\n"), "5\n");
+		assert_eq!(output_of(&mut runner, "\n"), "");
+		assert_eq!(output_of(&mut runner, "multiline off"), "");
+		assert_eq!(output_of(&mut runner, ""), "");
+		assert_eq!(output_of(&mut runner, "multiline"), "multiline is off\n");
+		assert_eq!(output_of(&mut runner, "1 +"), "Parse error: unexpected end of input.\n");
+	}
+	
+	#[test]
+	fn multiline_script_runs_at_end() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert!(runner.run_script("multiline on\na =\n  2\n\na *\n  3\n"));
+		assert_eq!(String::from_utf8(runner.output.clone()).unwrap(), "a = 2\n6\n");
+	}
+	
+	#[test]
+	fn bits_shows_ieee_754_fields() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "bits 1"), concat!(
+			"hex      0x3FF0000000000000\n",
+			"sign     0\n",
+			"exponent 01111111111 (1023)\n",
+			"mantissa 0000000000000000000000000000000000000000000000000000\n"));
+		assert_eq!(output_of(&mut runner, "bits 0"), concat!(
+			"hex      0x0000000000000000\n",
+			"sign     0\n",
+			"exponent 00000000000 (0)\n",
+			"mantissa 0000000000000000000000000000000000000000000000000000\n"));
+		assert_eq!(output_of(&mut runner, "bits -1.5"), concat!(
+			"hex      0xBFF8000000000000\n",
+			"sign     1\n",
+			"exponent 01111111111 (1023)\n",
+			"mantissa 1000000000000000000000000000000000000000000000000000\n"));
+	}
+	
+	#[test]
+	fn infinity_is_only_an_intermediate_value() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "1/inf"), "0\n");
+		assert_eq!(output_of(&mut runner, "arctan(inf)"), format!("{}\n", std::f64::consts::FRAC_PI_2));
+		assert_eq!(output_of(&mut runner, "inf"), "evaluation error: the result is infinite.\n");
+		assert_eq!(output_of(&mut runner, "x = -inf"), "evaluation error: the result is infinite.\n");
+		assert!(output_of(&mut runner, "bits inf").starts_with("hex      0x7FF0000000000000\n"));
+	}
+	
+	#[test]
+	fn stream_ends_at_end_of_input() {
+		assert_eq!(output_of_stream(""), "");
+		assert_eq!(output_of_stream("1 + 1\n2 + 2"), "2\n4\n");
+		assert_eq!(output_of_stream("multiline on\n1 +\n2"), "3\n");
+	}
+	
+	#[test]
+	fn stream_ends_at_quit() {
+		assert_eq!(output_of_stream("1 + 1\nquit\n2 + 2\n"), "2\n");
+	}
+	
+	#[test]
+	fn dump_recreates_state() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("precision 3");
+		runner.run_line("decimalpoint always");
+		runner.run_line("hex");
+		runner.run_line("a = 1/3");
+		runner.run_line("b = -a*3 + 1e-7");
+		runner.run_line("a = 2");
+		runner.run_line("total: a + b");
+		runner.run_line("alias r=sqrt");
+		runner.run_line("degrees");
+		runner.run_line("roundmode halfeven");
+		runner.run_line("errors verbose");
+		runner.run_line("multiline on");
+		runner.run_line("dump");
+		let dump = output_of(&mut runner, "");
+		assert_eq!(dump, concat!(
+			"precision 3\n",
+			"decimalpoint always\n",
+			"hex\n",
+			"autorecalc off\n",
+			"sigfigs off\n",
+			"numsteps 1000\n",
+			"degrees\n",
+			"roundmode halfeven\n",
+			"errors verbose\n",
+			"alias r=sqrt\n",
+			"a = 2  # from: 2\n",
+			"b = -0.9999999  # from: -a * 3 + 0.0000001\n",
+			"total: 1.0000000999999998\n",
+			"multiline on\n"));
+		
+		let mut restored = Runner::with_output(Vec::new());
+		assert!(restored.run_script(&dump));
+		assert_eq!(restored.context.variables, runner.context.variables);
+		assert_eq!(restored.context.labels, runner.context.labels);
+		assert!(restored.format.precision == runner.format.precision);
+		assert!(restored.format.always_decimal_point);
+		assert!(restored.format.base == Base::Hexadecimal);
+		assert!(restored.multiline);
+		assert!(restored.aliases == runner.aliases);
+		assert_eq!(restored.context.angle_mode, AngleMode::Degrees);
+		assert_eq!(restored.context.round_mode, RoundMode::HalfEven);
+		assert!(restored.verbose_errors);
+	}
+	
+	#[test]
+	fn why_describes_last_result() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "why"), "why: nothing has been computed yet.\n");
+		runner.run_line("2+2");
+		assert_eq!(output_of(&mut runner, "why"), "4 = 2 + 2 (addition)\n");
+		runner.run_line("x = sqrt(16)");
+		assert_eq!(output_of(&mut runner, "why"), "4 = sqrt(16) (function sqrt)\n");
+		runner.run_line("-(2x)");
+		assert_eq!(output_of(&mut runner, "why"), "-8 = -(2 * x) (negation)\n");
+		runner.run_line("peek 2^10");
+		runner.run_line("1/0");
+		assert_eq!(output_of(&mut runner, "why"), "-8 = -(2 * x) (negation)\n");
+	}
+	
+	#[test]
+	fn labeled_result_can_be_referenced() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("a = 2");
+		runner.run_line("b = -5");
+		runner.run_line("c = 3");
+		assert_eq!(output_of(&mut runner, "quadratic: (-b + sqrt(b^2 - 4a c)) / (2a)"), "quadratic: 1.5\n");
+		assert_eq!(output_of(&mut runner, "2quadratic + 1"), "4\n");
+		assert_eq!(output_of(&mut runner, "quadratic: 5"), "label quadratic is already defined.\n");
+		assert_eq!(output_of(&mut runner, "quadratic"), "1.5\n");
+		runner.run_line("ab = 1");
+		assert_eq!(output_of(&mut runner, "ab: 2"), "ab is already a variable.\n");
+	}
+	
+	#[test]
+	fn constant_is_not_confused_with_variables() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("p = 2");
+		runner.run_line("h = 3");
+		runner.run_line("i = 5");
+		assert_eq!(output_of(&mut runner, "phi"), "1.618033988749895\n");
+		assert_eq!(output_of(&mut runner, "p h i"), "30\n");
+	}
+	
+	#[test]
+	fn store_copies_last_result_to_variable() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "store x"), "store: nothing has been computed yet.\n");
+		runner.run_line("2+2");
+		assert_eq!(output_of(&mut runner, "store x"), "x = 4\n");
+		assert_eq!(output_of(&mut runner, "x"), "4\n");
+		assert_eq!(output_of(&mut runner, "vars def"), "x = 4  (from: 2 + 2)\n");
+		assert_eq!(output_of(&mut runner, "store pi"), "store: pi cannot be used as a variable.\n");
+		assert_eq!(output_of(&mut runner, "store"), "store: expected a variable name, e.g. 'store x'.\n");
+	}
+	
+	#[test]
+	fn integer_results_can_be_shown_in_hex_or_binary() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("hex");
+		assert_eq!(output_of(&mut runner, "255"), "0xff\n");
+		assert_eq!(output_of(&mut runner, "x = 0b1010 * 2"), "x = 0x14\n");
+		assert_eq!(output_of(&mut runner, "5 / 2"), "2.5 (not an integer, so in decimal)\n");
+		assert_eq!(output_of(&mut runner, "2^70"), "1180591620717411300000 (too large for 64 bits, so in decimal)\n");
+		runner.run_line("bin");
+		assert_eq!(output_of(&mut runner, "-x"), "-0b10100\n");
+		runner.run_line("dec");
+		assert_eq!(output_of(&mut runner, "0xff"), "255\n");
+	}
+	
+	#[test]
+	fn history_lists_input_lines() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("x = 2");
+		runner.run_line("");
+		runner.run_line("  x + 1\n");
+		runner.run_line("1 +");
+		assert_eq!(output_of(&mut runner, "history"), "   1  x = 2\n   2  x + 1\n   3  1 +\n");
+		assert_eq!(output_of(&mut runner, "history"), "   1  x = 2\n   2  x + 1\n   3  1 +\n   4  history\n");
+	}
+	
+	#[test]
+	fn edited_lines_are_run() {
+		let mut runner = Runner::with_output(Vec::new());
+		let mut editor = LineEditor::new(&b"x = 2\n\x1b[A\x7f5\nx + 1\x01\x042\nx_long = 7\nsq\t(x_l\t)\n"[..], Vec::new());
+		runner.run_editor(&mut editor).expect("input error!");
+		assert_eq!(String::from_utf8(runner.output).expect("output is not UTF-8!"), "x = 2\nx = 5\n3\nx_long = 7\n2.6457513110645907\n");
+	}
+	
+	#[test]
+	fn recall_runs_history_line_again() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "!1"), "!1: there is no line 1 in the history.\n");
+		runner.run_line("x = 2");
+		runner.run_line("x = x * 3");
+		assert_eq!(output_of(&mut runner, "!3"), "x = x * 3\nx = 18\n");
+		assert_eq!(output_of(&mut runner, "!4"), "!3\nx = x * 3\nx = 54\n");
+		assert_eq!(output_of(&mut runner, "3!"), "6\n");
+		assert_eq!(output_of(&mut runner, "!9"), "!9: there is no line 9 in the history.\n");
+	}
+	
+	#[test]
+	fn recall_of_itself_is_an_error() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "!1"), "!1: there is no line 1 in the history.\n");
+		let output = output_of(&mut runner, "!1");
+		assert!(output.ends_with("!1: too many recalls within recalls; does line 1 recall itself?\n"), "{}", output);
+		assert_eq!(output.lines().count(), MAX_RECALL_DEPTH + 1);
+		assert_eq!(output_of(&mut runner, "2 + 2"), "4\n");
+	}
+	
+	#[test]
+	fn earlier_history_can_be_recalled() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.set_history(vec![String::from("2 + 3")]);
+		assert_eq!(output_of(&mut runner, "!1"), "2 + 3\n5\n");
+		assert_eq!(runner.history(), ["2 + 3", "!1"]);
+	}
+	
+	#[test]
+	fn errors_are_shown_tersely_or_with_a_caret() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "errors"), "errors is terse\n");
+		assert_eq!(output_of(&mut runner, "2 + * 3"), "Parse error: unexpected operator '*'; expected a value.\n");
+		runner.run_line("errors verbose");
+		assert_eq!(output_of(&mut runner, "errors"), "errors is verbose\n");
+		assert_eq!(output_of(&mut runner, "2 + * 3"), "Parse error: unexpected operator '*'; expected a value.\n2 + * 3\n    ^\n");
+		assert_eq!(output_of(&mut runner, "x = 1; y = 2 $"), "x = 1\nSyntax error: unrecognized character: $.\n y = 2 $\n       ^\n");
+		assert_eq!(output_of(&mut runner, "1 / 0"), "evaluation error: arithmetic overflow during division.\n");
+		runner.run_line("errors terse");
+		assert_eq!(output_of(&mut runner, "1 +"), "Parse error: unexpected end of input.\n");
+		assert_eq!(output_of(&mut runner, "errors loud"), "errors: expected 'terse' or 'verbose'.\n");
+	}
+	
+	#[test]
+	fn variables_and_aliases_are_completed() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("maxval = 3");
+		runner.run_line("alias mag=abs");
+		assert_eq!(runner.complete("2 * ma"), (4, vec![String::from("mag"), String::from("max"), String::from("maxval")]));
+	}
+	
+	#[test]
+	fn value_commands_can_be_assigned() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "count"), "0\n");
+		runner.run_line("a = 1");
+		runner.run_line("b = 2");
+		assert_eq!(output_of(&mut runner, "count"), "2\n");
+		assert_eq!(output_of(&mut runner, "n = count"), "n = 2\n");
+		assert_eq!(output_of(&mut runner, "count + 1"), "4\n");
+		assert_eq!(output_of(&mut runner, "numsteps / 2"), "500\n");
+		assert_eq!(output_of(&mut runner, "m = 2 * numsteps"), "m = 2000\n");
+		assert_eq!(output_of(&mut runner, "numsteps * 2"), "2000\n");
+		assert_eq!(output_of(&mut runner, "count - 1"), "3\n");
+		assert_eq!(output_of(&mut runner, "numsteps -1"), "numsteps: expected a number of steps from 1 to 1000000.\n");
+		assert_eq!(output_of(&mut runner, "numsteps (2)"), "numsteps: expected a number of steps from 1 to 1000000.\n");
+		assert_eq!(output_of(&mut runner, "numsteps +5"), "");
+		assert_eq!(output_of(&mut runner, "numsteps"), "numsteps is 5\n");
+		assert_eq!(output_of(&mut runner, "x = help"), "Parse error: unexpected command help; expected a value.\n");
+	}
+	
+	#[test]
+	fn roundmode_changes_round() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "roundmode"), "roundmode is halfaway\n");
+		assert_eq!(output_of(&mut runner, "round(2.5)"), "3\n");
+		runner.run_line("roundmode halfeven");
+		assert_eq!(output_of(&mut runner, "round(2.5)"), "2\n");
+		runner.run_line("roundmode floor");
+		assert_eq!(output_of(&mut runner, "round(2.9)"), "2\n");
+		assert_eq!(output_of(&mut runner, "roundmode"), "roundmode is floor\n");
+		assert_eq!(output_of(&mut runner, "roundmode up"), "roundmode: expected one of halfaway, halfeven, halfup, halfdown, ceil, floor, trunc.\n");
+	}
+	
+	#[test]
+	fn choices_repeat_under_same_seed() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("seed 42");
+		let first: Vec<String> = (0..10).map(|_| output_of(&mut runner, "choose(1, 2, 3, 4, 5)")).collect();
+		runner.run_line("seed 42");
+		let second: Vec<String> = (0..10).map(|_| output_of(&mut runner, "choose(1, 2, 3, 4, 5)")).collect();
+		assert_eq!(first, second);
+		assert!(first.iter().all(|choice| ["1\n", "2\n", "3\n", "4\n", "5\n"].contains(&choice.as_str())));
+		assert!(first.iter().any(|choice| *choice != first[0]));
+		assert_eq!(output_of(&mut runner, "seed -1"), "seed: expected a nonnegative integer, e.g. 'seed 42'.\n");
+		assert_eq!(output_of(&mut runner, "seed"), "seed: expected a nonnegative integer, e.g. 'seed 42'.\n");
+	}
+	
+	#[test]
+	fn degree_mode_applies_to_trigonometric_functions() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "sin(pi/2)"), "1\n");
+		runner.run_line("degrees");
+		assert_eq!(output_of(&mut runner, "sin(90)"), "1\n");
+		assert_eq!(output_of(&mut runner, "cos(180)"), "-1\n");
+		assert_eq!(output_of(&mut runner, "tan(45)").trim().parse::<f64>().map(|v| (v - 1.0).abs() < 1e-12), Ok(true));
+		assert_eq!(output_of(&mut runner, "arcsin(1)"), "90\n");
+		assert_eq!(output_of(&mut runner, "arccos(-1)"), "180\n");
+		assert_eq!(output_of(&mut runner, "arctan(1)"), "45\n");
+		assert_eq!(output_of(&mut runner, "atan2(1, 0)"), "90\n");
+		assert_eq!(output_of(&mut runner, "sind(90) + sinh(0)"), "1\n");
+		runner.run_line("radians");
+		assert_eq!(output_of(&mut runner, "arccos(-1)"), format!("{}\n", std::f64::consts::PI));
+	}
+	
+	#[test]
+	fn more_steps_make_integrals_more_accurate() {
+		let mut runner = Runner::with_output(Vec::new());
+		let mut previous_error = f64::INFINITY;
+		
+		for steps in [1, 2, 4, 8] {
+			runner.run_line(&format!("numsteps {steps}"));
+			runner.run_line("integ(x, 0, 1, exp(x))");
+			let error = (runner.context.ans.unwrap() - (std::f64::consts::E - 1.0)).abs();
+			assert!(error < previous_error, "{} steps", steps);
+			previous_error = error;
+		}
+		
+		assert_eq!(output_of(&mut runner, "numsteps"), "numsteps is 8\n");
+		assert_eq!(output_of(&mut runner, "numsteps 0"), "numsteps: expected a number of steps from 1 to 1000000.\n");
+		assert_eq!(output_of(&mut runner, "numsteps many"), "numsteps: expected a number of steps from 1 to 1000000.\n");
+		assert_eq!(output_of(&mut runner, "numsteps"), "numsteps is 8\n");
+	}
+	
+	#[test]
+	fn step_shows_each_operation() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "step 2*(3+4)"), "3 + 4 = 7\n2 * 7 = 14\n");
+		runner.run_line("x = 9");
+		assert_eq!(output_of(&mut runner, "step -sqrt(x) + max(1, 2^3)"), concat!(
+			"sqrt(9) = 3\n",
+			"-3 = -3\n",
+			"2 ^ 3 = 8\n",
+			"max(1, 8) = 8\n",
+			"-3 + 8 = 5\n"));
+		assert_eq!(output_of(&mut runner, "step if(x, 1, 1/0) * 2"), "if(x, 1, 1 / 0) = 1\n1 * 2 = 2\n");
+		assert_eq!(output_of(&mut runner, "step (1 + 1) / (2 - 2)"), "1 + 1 = 2\n2 - 2 = 0\nevaluation error: arithmetic overflow during division.\n");
+		assert_eq!(output_of(&mut runner, "ans"), "9\n");
+	}
+	
+	#[test]
+	fn step_shows_value_without_operations() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("x = 1");
+		assert_eq!(output_of(&mut runner, "step x"), "x = 1\n");
+		assert_eq!(output_of(&mut runner, "step 5"), "5\n");
+		assert_eq!(output_of(&mut runner, "step -3"), "-3\n");
+		assert_eq!(output_of(&mut runner, "step -3 * x"), "-3 * 1 = -3\n");
+	}
+	
+	#[test]
+	fn reduce_hides_variable_of_same_name() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("i = 7");
+		assert_eq!(output_of(&mut runner, "reduce(+, i, 1, 3, 1, i)"), "6\n");
+		assert_eq!(output_of(&mut runner, "i"), "7\n");
+		assert_eq!(output_of(&mut runner, "reduce(+, j, 1, 3, 1, j)"), "6\n");
+		assert_eq!(output_of(&mut runner, "j"), "evaluation error: variable j is undefined.\n");
+	}
+	
+	#[test]
+	fn alias_names_a_function() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("alias r=sqrt");
+		assert_eq!(output_of(&mut runner, "r(4)"), "2\n");
+		assert_eq!(output_of(&mut runner, "2r(9) + sqrt(9)"), "9\n");
+		assert!(output_of(&mut runner, "r = 1").starts_with("Parse error"));
+		assert_eq!(output_of(&mut runner, "store r"), "store: r cannot be used as a variable.\n");
+		runner.run_line("alias r = cbrt");
+		assert_eq!(output_of(&mut runner, "alias r = ln"), "");
+		assert_eq!(output_of(&mut runner, "alias"), "alias r=ln\n");
+	}
+	
+	#[test]
+	fn alias_rejects_unknown_functions_and_taken_names() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("x = 1");
+		runner.run_line("total: 2");
+		assert_eq!(output_of(&mut runner, "alias r=foo"), "alias: foo is not a function.\n");
+		assert_eq!(output_of(&mut runner, "alias r=pi"), "alias: pi is not a function.\n");
+		assert_eq!(output_of(&mut runner, "alias sin=cos"), "alias: sin is already in use.\n");
+		assert_eq!(output_of(&mut runner, "alias pi=sqrt"), "alias: pi is already in use.\n");
+		assert_eq!(output_of(&mut runner, "alias quit=sqrt"), "alias: quit is already in use.\n");
+		assert_eq!(output_of(&mut runner, "alias ans=sqrt"), "alias: ans is already in use.\n");
+		assert_eq!(output_of(&mut runner, "alias total=sqrt"), "alias: total is already in use.\n");
+		assert_eq!(output_of(&mut runner, "alias x=sqrt"), "alias: x is already a variable.\n");
+		assert_eq!(output_of(&mut runner, "alias 2r=sqrt"), "alias: '2r' is not a valid name.\n");
+		assert_eq!(output_of(&mut runner, "alias r sqrt"), "alias: expected a name and a function, e.g. 'alias r=sqrt'.\n");
+		assert_eq!(output_of(&mut runner, "alias"), "");
+	}
+	
+	#[test]
+	fn stat_reports_accumulated_values() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "stat"), "stat: no values have been added.\n");
+		assert_eq!(output_of(&mut runner, "stat add 2"), "added 2, count 1\n");
+		runner.run_line("x = 3");
+		runner.run_line("stat add 2x - 4");
+		runner.run_line("stat add 2^3");
+		runner.run_line("stat add 1/0");
+		assert_eq!(output_of(&mut runner, "stat"), "count  3\nmean   4\nmin    2\nmax    8\nstddev 3.4641016151377544\n");
+		runner.run_line("stat clear");
+		runner.run_line("stat add 5");
+		assert_eq!(output_of(&mut runner, "stat"), "count  1\nmean   5\nmin    5\nmax    5\nstddev 0\n");
+		assert_eq!(output_of(&mut runner, "stat foo"), "stat: expected 'add <expr>', 'clear' or nothing.\n");
+	}
+	
+	#[test]
+	fn percentage_is_taken_of_latest_result() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "+10%"), "nothing has been computed yet to take a percentage of.\n");
+		runner.run_line("200");
+		assert_eq!(output_of(&mut runner, "+10%"), "220\n");
+		assert_eq!(output_of(&mut runner, "-5%"), "209\n");
+		assert_eq!(output_of(&mut runner, "why"), "209 = 220 - 220 * (5) / 100 (subtraction)\n");
+		assert_eq!(output_of(&mut runner, "-5 % 3"), "1\n");
+	}
+	
+	#[test]
+	fn convert_shows_value_in_other_unit() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "convert 180 deg rad"), format!("{} rad\n", std::f64::consts::PI));
+		runner.run_line("x = 2");
+		assert_eq!(output_of(&mut runner, "convert 5x + 230 min h"), "4 h\n");
+		assert_eq!(output_of(&mut runner, "convert 1 deg km"), "convert: cannot convert deg to km.\n");
+		assert_eq!(output_of(&mut runner, "convert 1 km"), "Parse error: convert: expected a value and two units, e.g. 'convert 1 mi km'.\n");
+		assert_eq!(output_of(&mut runner, "convert 2 + km m"), "Parse error: unexpected end of input.\n");
+		assert_eq!(runner.context.ans, Some(2.0));
+	}
+	
+	#[test]
+	fn ans_is_the_latest_result() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "ans"), "evaluation error: variable ans is undefined.\n");
+		runner.run_line("2+2");
+		assert_eq!(output_of(&mut runner, "ans * 2"), "8\n");
+		assert_eq!(output_of(&mut runner, "x = ans + 1"), "x = 9\n");
+		assert_eq!(output_of(&mut runner, "peek ans"), "9\n");
+		assert_eq!(output_of(&mut runner, "1/0"), "evaluation error: arithmetic overflow during division.\n");
+		assert_eq!(output_of(&mut runner, "ans"), "9\n");
+		assert_eq!(output_of(&mut runner, "ans = 1"), "cannot assign to ans, which is the latest result.\n");
+		assert_eq!(output_of(&mut runner, "store ans"), "store: ans cannot be used as a variable.\n");
+		assert_eq!(output_of(&mut runner, "ans: 1"), "ans is the latest result and cannot be a label.\n");
+		assert_eq!(output_of(&mut runner, "vars"), "x = 9\n");
+		assert!(runner.unused_variables().contains(&String::from("x")));
+	}
+	
+	#[test]
+	fn statements_separated_by_semicolons_run_in_order() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "a = 2; b = 3; a + b"), "a = 2\nb = 3\n5\n");
+		assert_eq!(output_of(&mut runner, "a = 4;; a;"), "a = 4\n4\n");
+		assert_eq!(output_of(&mut runner, "vars; why"), "a = 4\nb = 3\n4 = a (value of variable a)\n");
+		assert_eq!(output_of(&mut runner, "cc: 1; 2cc"), "cc: 1\n2\n");
+	}
+	
+	#[test]
+	fn statements_stop_at_first_error_or_quit() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "x = 1; 2 +; x = 3"), "x = 1\nParse error: unexpected end of input.\n");
+		assert_eq!(output_of(&mut runner, "y; x = 3"), "evaluation error: variable y is undefined.\n");
+		assert_eq!(runner.context.variables["x"], 1.0);
+		assert!(!runner.run_line("x = 2; quit; x = 3"));
+		assert_eq!(runner.context.variables["x"], 2.0);
+	}
+	
+	#[test]
+	fn recompute_reassigns_from_definition() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("a = 2");
+		runner.run_line("b = a*3");
+		runner.run_line("a = 5");
+		assert_eq!(output_of(&mut runner, "b"), "6\n");
+		assert_eq!(output_of(&mut runner, "recompute b"), "b = 15\n");
+		assert_eq!(output_of(&mut runner, "why"), "15 = a * 3 (multiplication)\n");
+		assert_eq!(output_of(&mut runner, "b"), "15\n");
+		assert_eq!(output_of(&mut runner, "recompute c"), "recompute: c is not a variable.\n");
+		assert_eq!(output_of(&mut runner, "recompute"), "recompute: expected a variable name, e.g. 'recompute x'.\n");
+	}
+	
+	#[test]
+	fn autorecalc_updates_dependents_in_order() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("autorecalc on");
+		runner.run_line("a = 1");
+		runner.run_line("b = a*2");
+		runner.run_line("c = b + a");
+		runner.run_line("d = 5");
+		assert_eq!(output_of(&mut runner, "a = 10"), "a = 10\nb = 20\nc = 30\n");
+		assert_eq!(output_of(&mut runner, "b = 1"), "b = 1\nc = 11\n");
+		assert_eq!(output_of(&mut runner, "a = a + 1"), "a = 11\nc = 12\n");
+		runner.run_line("autorecalc off");
+		assert_eq!(output_of(&mut runner, "a = 0"), "a = 0\n");
+		assert_eq!(output_of(&mut runner, "autorecalc"), "autorecalc is off\n");
+	}
+	
+	#[test]
+	fn autorecalc_rejects_cyclic_dependencies() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("autorecalc on");
+		runner.run_line("a = 1");
+		runner.run_line("x = 1");
+		runner.run_line("b = a*2 + x");
+		runner.run_line("c = b + 1");
+		assert_eq!(output_of(&mut runner, "a = c"), "a cannot depend on c, which depends on a.\n");
+		assert_eq!(runner.context.variables["a"], 1.0);
+		
+		runner.run_line("autorecalc off");
+		runner.run_line("a = c");
+		runner.run_line("autorecalc on");
+		assert_eq!(output_of(&mut runner, "x = 2"), "x = 2\ncannot recalculate, as b, c, a depend on each other.\n");
+	}
+	
+	#[test]
+	fn clear_removes_variables() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("x = 5");
+		runner.run_line("total: 2x");
+		assert_eq!(output_of(&mut runner, "clear"), "");
+		assert_eq!(output_of(&mut runner, "x"), "evaluation error: variable x is undefined.\n");
+		assert_eq!(output_of(&mut runner, "vars def"), "");
+		assert_eq!(output_of(&mut runner, "total"), "10\n");
+		assert_eq!(output_of(&mut runner, "clear x"), "Parse error: extra characters at the end of line.\n");
+	}
+	
+	#[test]
+	fn del_removes_one_variable() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("x = 5");
+		runner.run_line("y = 2x");
+		assert_eq!(output_of(&mut runner, "del x"), "");
+		assert_eq!(output_of(&mut runner, "x"), "evaluation error: variable x is undefined.\n");
+		assert_eq!(output_of(&mut runner, "vars def"), "y = 10  (from: 2 * x)\n");
+		assert_eq!(output_of(&mut runner, "del x"), "del: x is not a variable.\n");
+		assert_eq!(output_of(&mut runner, "del"), "del: expected a variable name, e.g. 'del x'.\n");
+	}
+	
+	#[test]
+	fn assert_reports_only_failures() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(output_of(&mut runner, "assert 1 < 2"), "");
+		runner.run_line("x = 0.1 + 0.2");
+		assert_eq!(output_of(&mut runner, "assert abs(x - 0.3) < 1e-12"), "");
+		assert_eq!(runner.failed_assertions(), 0);
+		assert_eq!(output_of(&mut runner, "assert 2 < 1; x"), "assertion failed: 2 < 1\n");
+		assert_eq!(output_of(&mut runner, "assert x == 0.3"), "assertion failed: x == 0.3\n");
+		assert_eq!(output_of(&mut runner, "assert 1/0"), "evaluation error: arithmetic overflow during division.\n");
+		assert_eq!(runner.failed_assertions(), 3);
+		assert_eq!(runner.run_once("assert 2 < 1"), 1);
+		assert_eq!(runner.run_once("assert 1 < 2"), 0);
+	}
+	
+	#[test]
+	fn run_once_returns_exit_code() {
+		let mut runner = Runner::with_output(Vec::new());
+		assert_eq!(runner.run_once("2 + 2 * 3"), 0);
+		assert_eq!(runner.run_once("x = 2; x + y"), 1);
+		assert_eq!(runner.run_once("2 +"), 1);
+		assert_eq!(runner.run_once("precision x"), 1);
+		assert_eq!(runner.run_once(""), 0);
+		assert_eq!(runner.run_once("# nothing"), 0);
+		assert_eq!(String::from_utf8(runner.output.clone()).unwrap(), concat!(
+			"8\n",
+			"x = 2\n",
+			"evaluation error: variable y is undefined.\n",
+			"Parse error: unexpected end of input.\n",
+			"precision: expected the number of decimals, 'auto' or 'sci'.\n"));
+		
+		runner.run_line("multiline on");
+		assert_eq!(runner.run_once("x * 4"), 0);
+		assert!(String::from_utf8(runner.output.clone()).unwrap().ends_with("\n8\n"));
+	}
+	
+	#[test]
+	fn sigfigs_rounds_measured_results() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("precision 4");
+		assert_eq!(output_of(&mut runner, "2.0 * 3.00"), "6.0000\n");
+		runner.run_line("sigfigs on");
+		assert_eq!(output_of(&mut runner, "2.0 * 3.00"), "6.0\n");
+		assert_eq!(output_of(&mut runner, "x = 12.34 + 1.2"), "x = 13.5\n");
+		assert_eq!(output_of(&mut runner, "x * 2"), "30\n");
+		assert_eq!(output_of(&mut runner, "x / 3.00"), "4.51\n");
+		assert_eq!(output_of(&mut runner, "sqrt(2.0)"), "1.4142\n");
+		assert_eq!(runner.context.variables["x"], 12.34 + 1.2);
+		assert_eq!(output_of(&mut runner, "sigfigs"), "sigfigs is on\n");
+		runner.run_line("sigfigs off");
+		assert_eq!(output_of(&mut runner, "2.0 * 3.00"), "6.0000\n");
+	}
+	
+	#[test]
+	fn search_lists_matching_history_lines() {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_line("r = 2");
+		runner.run_line("area = pi * r^2");
+		runner.run_line("sqrt(area)");
+		runner.run_line("c = 2 * pi * r");
+		assert_eq!(output_of(&mut runner, "search pi * r"), "   2  area = pi * r^2\n   4  c = 2 * pi * r\n");
+		assert_eq!(output_of(&mut runner, "search area"), "   2  area = pi * r^2\n   3  sqrt(area)\n");
+		assert_eq!(output_of(&mut runner, "search tau"), "");
+		assert_eq!(output_of(&mut runner, "search"), "search: expected text to search for, e.g. 'search sqrt'.\n");
+		assert_eq!(output_of(&mut runner, "search searc"), "   5  search pi * r\n   6  search area\n   7  search tau\n   8  search\n");
+	}
+	
+	// Run the input on a new runner as if read from a file or a pipe, and
+	// return all of the output.
+	fn output_of_stream(input: &str) -> String {
+		let mut runner = Runner::with_output(Vec::new());
+		runner.run_stream(input.as_bytes()).expect("input error!");
+		String::from_utf8(runner.output).expect("output is not UTF-8!")
+	}
+	
+	// Run a line and return only the output it produced.
+	fn output_of(runner: &mut Runner<Vec<u8>>, line: &str) -> String {
+		runner.output.clear();
+		runner.run_line(line);
+		String::from_utf8(runner.output.clone()).expect("output is not UTF-8!")
+	}
 }
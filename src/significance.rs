@@ -0,0 +1,219 @@
+// Significant figures of results computed from measured values, for the
+// sigfigs command. Number literals count as measurements whose precision
+// is given by how they are written: "2.0" has two significant figures and
+// "2.00" three. Variables, constants and labels count as exact.
+
+use crate::errors::Error;
+use crate::evaluation::*;
+use crate::lexer::*;
+use crate::parser::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Significance {
+	// No measured value is involved.
+	Exact,
+	// A measured value went through an operation other than +, -, * or /.
+	Unknown,
+	// The number of significant figures, and the power of ten of the last
+	// one: 12.5 has 3 figures, the last at 10^-1.
+	Measured { figures: i32, last: i32 }
+}
+
+// Find out the significance of the value of an expression using the usual
+// rules: a sum or a difference is as precise as its least precise operand
+// in terms of decimal places, and a product or a quotient has as many
+// significant figures as its operand with the fewest of them.
+pub fn significance(expression: &Expression, context: &mut Context) -> Result<Significance, Error> {
+	Ok(measure(expression, context)?.1)
+}
+
+// Format a value rounded to its last significant figure.
+pub fn format_measured(value: f64, last: i32) -> String {
+	let scale = 10.0_f64.powi(last);
+	let decimals = if last < 0 { -last as usize } else { 0 };
+	format!("{:.*}", decimals, (value / scale).round() * scale)
+}
+
+fn measure(expression: &Expression, context: &mut Context) -> Result<(f64, Significance), Error> {
+	match expression {
+		Expression::ParenExpr(e) => measure(&e.expr, context),
+		Expression::UnaryExpr(e) => {
+			let (value, significance) = measure(&e.expr, context)?;
+			Ok((if e.op == OperatorType::Minus { -value } else { value }, significance))
+		},
+		Expression::BinaryExpr(e) => {
+			let (left, left_significance) = measure(&e.left, context)?;
+			let (right, right_significance) = measure(&e.right, context)?;
+			let value = e.apply(left, right)?;
+			Ok((value, combine(e.op, value, left_significance, right_significance)))
+		},
+		Expression::LiteralExpr(e) => {
+			Ok((e.val, e.text.as_deref().map_or(Significance::Exact, literal_significance)))
+		},
+		Expression::FunctionExpr(e) => {
+			let significance = if e.args.iter().all(is_exact) { Significance::Exact } else { Significance::Unknown };
+			Ok((expression.evaluate(context)?, significance))
+		},
+		Expression::ReduceExpr(_) | Expression::IntegralExpr(_) => {
+			let significance = if is_exact(expression) { Significance::Exact } else { Significance::Unknown };
+			Ok((expression.evaluate(context)?, significance))
+		},
+		Expression::VariableExpr(_) | Expression::ConstantExpr(_) | Expression::LabelExpr(_) | Expression::EnvExpr(_) | Expression::CommandValueExpr(_) => {
+			Ok((expression.evaluate(context)?, Significance::Exact))
+		}
+	}
+}
+
+fn combine(op: OperatorType, value: f64, left: Significance, right: Significance) -> Significance {
+	use Significance::*;
+	
+	match (left, right) {
+		(Exact, Exact) => Exact,
+		(Unknown, _) | (_, Unknown) => Unknown,
+		_ => match op {
+			OperatorType::Plus | OperatorType::Minus => {
+				let last = [left, right].iter().filter_map(|s| match s {
+					Measured { last, .. } => Some(*last),
+					_ => None
+				}).max().unwrap();
+				let figures = if value == 0.0 { 1 } else { (magnitude(value) - last + 1).max(1) };
+				Measured { figures, last }
+			},
+			OperatorType::Times | OperatorType::DividedBy => {
+				let figures = [left, right].iter().filter_map(|s| match s {
+					Measured { figures, .. } => Some(*figures),
+					_ => None
+				}).min().unwrap();
+				let last = if value == 0.0 { 1 - figures } else { magnitude(value) - figures + 1 };
+				Measured { figures, last }
+			},
+			_ => Unknown
+		}
+	}
+}
+
+// The power of ten of the first digit of a nonzero value.
+fn magnitude(value: f64) -> i32 {
+	value.abs().log10().floor() as i32
+}
+
+// Whether an expression has no number literals in it, apart from ones
+// that weren't written as such.
+fn is_exact(expression: &Expression) -> bool {
+	match expression {
+		Expression::ParenExpr(e) => is_exact(&e.expr),
+		Expression::UnaryExpr(e) => is_exact(&e.expr),
+		Expression::BinaryExpr(e) => is_exact(&e.left) && is_exact(&e.right),
+		Expression::FunctionExpr(e) => e.args.iter().all(is_exact),
+		Expression::ReduceExpr(e) => [&e.lo, &e.hi, &e.step, &e.body].into_iter().all(is_exact),
+		Expression::IntegralExpr(e) => [&e.lo, &e.hi, &e.body].into_iter().all(is_exact),
+		Expression::LiteralExpr(e) => e.text.is_none(),
+		Expression::VariableExpr(_) | Expression::ConstantExpr(_) | Expression::LabelExpr(_) | Expression::EnvExpr(_) | Expression::CommandValueExpr(_) => true
+	}
+}
+
+// Count the significant figures of a literal as written. Leading zeros are
+// never significant. Trailing zeros are if there is a decimal point, so
+// 1200 has two significant figures but 1200. and 1200.0 have four and
+// five. Integer literals in another radix, like 0xff, are exact.
+fn literal_significance(text: &str) -> Significance {
+	if text.starts_with('0') && text[1..].starts_with(['x', 'X', 'b', 'B', 'o', 'O']) {
+		return Significance::Exact;
+	}
+	
+	let text = text.replace('_', "");
+	let (mantissa, exponent) = match text.find(['e', 'E']) {
+		Some(e) => (&text[..e], text[e + 1..].parse::<i32>().unwrap_or(0)),
+		None => (&text[..], 0)
+	};
+	
+	let (integers, decimals) = match mantissa.find('.') {
+		Some(point) => (&mantissa[..point], Some(&mantissa[point + 1..])),
+		None => (mantissa, None)
+	};
+	
+	let digits = String::from(integers) + decimals.unwrap_or("");
+	let significant = digits.trim_start_matches('0');
+	
+	let (figures, last) = match decimals {
+		Some(decimals) => (significant.len() as i32, exponent - decimals.len() as i32),
+		None => {
+			let trimmed = significant.trim_end_matches('0');
+			(trimmed.len() as i32, exponent + (significant.len() - trimmed.len()) as i32)
+		}
+	};
+	
+	Significance::Measured { figures: figures.max(1), last }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn literal_figures_are_counted() {
+		let expected = [
+			("2", 1, 0),
+			("2.0", 2, -1),
+			("3.00", 3, -2),
+			("0.0120", 3, -4),
+			("1200", 2, 2),
+			("1200.", 4, 0),
+			("1.50e3", 3, 1),
+			("2.5E-3", 2, -4),
+			("1_000.5", 5, -1),
+			("0", 1, 0)
+		];
+		
+		for (text, figures, last) in expected {
+			assert_eq!(literal_significance(text), Significance::Measured { figures, last }, "{}", text);
+		}
+		
+		assert_eq!(literal_significance("0xff"), Significance::Exact);
+		assert_eq!(literal_significance("0b101"), Significance::Exact);
+	}
+	
+	#[test]
+	fn products_keep_fewest_figures() {
+		assert_eq!(display("2.0 * 3.00"), "6.0");
+		assert_eq!(display("1.23 / 3"), "0.4");
+		assert_eq!(display("4.0 * pi"), "13");
+		assert_eq!(display("12.0 * 3.000 / 2.0000"), "18.0");
+	}
+	
+	#[test]
+	fn sums_keep_fewest_decimals() {
+		assert_eq!(display("12.34 + 1.2"), "13.5");
+		assert_eq!(display("100.0 - 0.25"), "99.8");
+		assert_eq!(display("1200 + 3.5"), "1200");
+		assert_eq!(display("-(1.5 + 2.25)"), "-3.8");
+	}
+	
+	#[test]
+	fn other_operations_are_not_tracked() {
+		assert_eq!(measured("sqrt(2.0)"), Significance::Unknown);
+		assert_eq!(measured("2.0^2"), Significance::Unknown);
+		assert_eq!(measured("sqrt(2.0) * 3.0"), Significance::Unknown);
+		assert_eq!(measured("pi * 2 / 2"), Significance::Measured { figures: 1, last: 0 });
+		assert_eq!(measured("pi + sqrt(pi)"), Significance::Exact);
+	}
+	
+	fn measured(line: &str) -> Significance {
+		match Parser::new(line).parse().expect("expression doesn't parse!") {
+			Program::Expr(expr) => significance(&expr, &mut Context::new()).expect("expression doesn't evaluate!"),
+			_ => { panic!("not an expression!"); }
+		}
+	}
+	
+	fn display(line: &str) -> String {
+		let value = match Parser::new(line).parse().expect("expression doesn't parse!") {
+			Program::Expr(expr) => expr.evaluate(&mut Context::new()).expect("expression doesn't evaluate!"),
+			_ => { panic!("not an expression!"); }
+		};
+		
+		match measured(line) {
+			Significance::Measured { last, .. } => format_measured(value, last),
+			other => { panic!("{} is {:?}!", line, other); }
+		}
+	}
+}
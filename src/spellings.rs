@@ -0,0 +1,132 @@
+// The spellings of commands, functions and constants, shared by the lexer,
+// which recognizes them, and tab completion, which offers them.
+
+use crate::lexer::CommandType;
+use crate::lexer::ConstantType;
+use crate::lexer::FunctionType;
+
+pub const COMMAND_SPELLINGS: &[(&str, CommandType)] = &[
+	("alias", CommandType::Alias),
+	("assert", CommandType::Assert),
+	("autorecalc", CommandType::AutoRecalc),
+	("bin", CommandType::Bin),
+	("bits", CommandType::Bits),
+	("clear", CommandType::Clear),
+	("convert", CommandType::Convert),
+	("count", CommandType::Count),
+	("dec", CommandType::Dec),
+	("decimalpoint", CommandType::DecimalPoint),
+	("degrees", CommandType::Degrees),
+	("del", CommandType::Del),
+	("dump", CommandType::Dump),
+	("errors", CommandType::Errors),
+	("help", CommandType::Help),
+	("hex", CommandType::Hex),
+	("history", CommandType::History),
+	("load", CommandType::Load),
+	("multiline", CommandType::Multiline),
+	("numsteps", CommandType::NumSteps),
+	("peek", CommandType::Peek),
+	("prec", CommandType::Prec),
+	("precision", CommandType::Precision),
+	("quit", CommandType::Quit),
+	("radians", CommandType::Radians),
+	("recompute", CommandType::Recompute),
+	("roundmode", CommandType::RoundMode),
+	("save", CommandType::Save),
+	("search", CommandType::Search),
+	("seed", CommandType::Seed),
+	("sigfigs", CommandType::SigFigs),
+	("stat", CommandType::Stat),
+	("step", CommandType::Step),
+	("store", CommandType::Store),
+	("vars", CommandType::Vars),
+	("why", CommandType::Why)
+];
+
+pub const FUNCTION_SPELLINGS: &[(&str, FunctionType)] = &[
+	("abs", FunctionType::Abs),
+	("acosd", FunctionType::AcosDeg),
+	("acosh", FunctionType::Acosh),
+	("arccos", FunctionType::ArcCos),
+	("arcsin", FunctionType::ArcSin),
+	("arctan", FunctionType::ArcTan),
+	("asind", FunctionType::AsinDeg),
+	("asinh", FunctionType::Asinh),
+	("atan2", FunctionType::Atan2),
+	("atand", FunctionType::AtanDeg),
+	("atanh", FunctionType::Atanh),
+	("avg", FunctionType::Mean),
+	("binompmf", FunctionType::BinomPmf),
+	("ceil", FunctionType::Ceil),
+	("ceilto", FunctionType::CeilTo),
+	("choose", FunctionType::Choose),
+	("clamp", FunctionType::Clamp),
+	("cos", FunctionType::Cos),
+	("cosd", FunctionType::CosDeg),
+	("cosh", FunctionType::Cosh),
+	("costurn", FunctionType::CosTurn),
+	("deg", FunctionType::Deg),
+	("exp", FunctionType::Exp),
+	("expm1", FunctionType::ExpM1),
+	("fact", FunctionType::Fact),
+	("floor", FunctionType::Floor),
+	("floorto", FunctionType::FloorTo),
+	("gcd", FunctionType::Gcd),
+	("getenv", FunctionType::GetEnv),
+	("if", FunctionType::If),
+	("integ", FunctionType::Integ),
+	("invlerp", FunctionType::InvLerp),
+	("isprime", FunctionType::IsPrime),
+	("kthlargest", FunctionType::KthLargest),
+	("kthsmallest", FunctionType::KthSmallest),
+	("lcm", FunctionType::Lcm),
+	("lerp", FunctionType::Lerp),
+	("ln", FunctionType::Ln),
+	("ln1p", FunctionType::Ln1P),
+	("log", FunctionType::Log),
+	("log2", FunctionType::Log2),
+	("max", FunctionType::Max),
+	("mean", FunctionType::Mean),
+	("min", FunctionType::Min),
+	("mod", FunctionType::Mod),
+	("ncr", FunctionType::Ncr),
+	("nextprime", FunctionType::NextPrime),
+	("npr", FunctionType::Npr),
+	("percentile", FunctionType::Percentile),
+	("poissonpmf", FunctionType::PoissonPmf),
+	("pow", FunctionType::Pow),
+	("powmod", FunctionType::PowMod),
+	("product", FunctionType::Product),
+	("quadroot1", FunctionType::QuadRoot1),
+	("quadroot2", FunctionType::QuadRoot2),
+	("rad", FunctionType::Rad),
+	("reduce", FunctionType::Reduce),
+	("root", FunctionType::Root),
+	("round", FunctionType::Round),
+	("sign", FunctionType::Sign),
+	("sin", FunctionType::Sin),
+	("sind", FunctionType::SinDeg),
+	("sinh", FunctionType::Sinh),
+	("sinturn", FunctionType::SinTurn),
+	("sqrt", FunctionType::Sqrt),
+	("sum", FunctionType::Sum),
+	("tan", FunctionType::Tan),
+	("tand", FunctionType::TanDeg),
+	("tanh", FunctionType::Tanh),
+	("tanturn", FunctionType::TanTurn),
+	("trunc", FunctionType::Trunc)
+];
+
+// A constant must form a whole word, so "e" alone is Euler's number but
+// the "e" in "1e3" is an exponent separator and "ex" is two variables.
+pub const CONSTANT_SPELLINGS: &[(&str, ConstantType)] = &[
+	("e", ConstantType::E),
+	("eps", ConstantType::Eps),
+	("inf", ConstantType::Inf),
+	("maxfloat", ConstantType::MaxFloat),
+	("minfloat", ConstantType::MinFloat),
+	("phi", ConstantType::Phi),
+	("pi", ConstantType::Pi),
+	("tau", ConstantType::Tau)
+];
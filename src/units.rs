@@ -0,0 +1,114 @@
+// Units for the convert command. Each unit is defined by how a value in it
+// is converted to the base unit of its quantity: base = value * factor +
+// offset. Only temperatures need an offset.
+
+use crate::errors::Error;
+use std::f64::consts::PI;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Quantity {
+	Angle,
+	Length,
+	Mass,
+	Temperature,
+	Time
+}
+
+struct Unit {
+	name: &'static str,
+	quantity: Quantity,
+	factor: f64,
+	offset: f64
+}
+
+const fn unit(name: &'static str, quantity: Quantity, factor: f64) -> Unit {
+	Unit { name, quantity, factor, offset: 0.0 }
+}
+
+// Base units are radians, meters, kilograms, kelvins and seconds. A new
+// unit only needs an entry here.
+const UNITS: &[Unit] = &[
+	unit("rad", Quantity::Angle, 1.0),
+	unit("deg", Quantity::Angle, PI / 180.0),
+	unit("grad", Quantity::Angle, PI / 200.0),
+	unit("turn", Quantity::Angle, 2.0 * PI),
+	unit("m", Quantity::Length, 1.0),
+	unit("km", Quantity::Length, 1000.0),
+	unit("cm", Quantity::Length, 0.01),
+	unit("mm", Quantity::Length, 0.001),
+	unit("in", Quantity::Length, 0.0254),
+	unit("ft", Quantity::Length, 0.3048),
+	unit("yd", Quantity::Length, 0.9144),
+	unit("mi", Quantity::Length, 1609.344),
+	unit("nmi", Quantity::Length, 1852.0),
+	unit("kg", Quantity::Mass, 1.0),
+	unit("g", Quantity::Mass, 0.001),
+	unit("lb", Quantity::Mass, 0.45359237),
+	unit("oz", Quantity::Mass, 0.028349523125),
+	unit("k", Quantity::Temperature, 1.0),
+	Unit { name: "c", quantity: Quantity::Temperature, factor: 1.0, offset: 273.15 },
+	Unit { name: "f", quantity: Quantity::Temperature, factor: 5.0 / 9.0, offset: 273.15 - 32.0 * 5.0 / 9.0 },
+	unit("s", Quantity::Time, 1.0),
+	unit("min", Quantity::Time, 60.0),
+	unit("h", Quantity::Time, 3600.0),
+	unit("d", Quantity::Time, 86400.0)
+];
+
+pub fn convert(value: f64, from: &str, to: &str) -> Result<f64, Error> {
+	let from_unit = find_unit(from)?;
+	let to_unit = find_unit(to)?;
+	
+	if from_unit.quantity != to_unit.quantity {
+		return Err(Error::new(&format!("convert: cannot convert {} to {}.", from, to)));
+	}
+	
+	let base = value * from_unit.factor + from_unit.offset;
+	Ok((base - to_unit.offset) / to_unit.factor)
+}
+
+fn find_unit(name: &str) -> Result<&'static Unit, Error> {
+	UNITS.iter()
+		.find(|u| u.name == name)
+		.ok_or_else(|| Error::new(&format!("convert: unknown unit {}.", name)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_approx_eq::assert_approx_eq;
+	
+	#[test]
+	fn angles_are_converted() {
+		assert_approx_eq!(convert(180.0, "deg", "rad").unwrap(), PI);
+		assert_approx_eq!(convert(0.25, "turn", "deg").unwrap(), 90.0);
+		assert_approx_eq!(convert(100.0, "grad", "deg").unwrap(), 90.0);
+	}
+	
+	#[test]
+	fn lengths_and_masses_are_converted() {
+		assert_approx_eq!(convert(1.0, "mi", "km").unwrap(), 1.609344);
+		assert_approx_eq!(convert(12.0, "in", "ft").unwrap(), 1.0);
+		assert_approx_eq!(convert(1.0, "lb", "oz").unwrap(), 16.0);
+	}
+	
+	#[test]
+	fn temperatures_are_converted_with_offsets() {
+		assert_approx_eq!(convert(100.0, "c", "f").unwrap(), 212.0);
+		assert_approx_eq!(convert(-40.0, "f", "c").unwrap(), -40.0);
+		assert_approx_eq!(convert(0.0, "k", "c").unwrap(), -273.15);
+	}
+	
+	#[test]
+	fn incompatible_or_unknown_units_are_errors() {
+		assert_eq!(convert(1.0, "deg", "km").unwrap_err().description, "convert: cannot convert deg to km.");
+		assert_eq!(convert(1.0, "parsec", "km").unwrap_err().description, "convert: unknown unit parsec.");
+	}
+	
+	#[test]
+	fn unit_names_are_unique() {
+		let mut names: Vec<&str> = UNITS.iter().map(|u| u.name).collect();
+		names.sort();
+		names.dedup();
+		assert_eq!(names.len(), UNITS.len());
+	}
+}